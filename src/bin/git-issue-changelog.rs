@@ -0,0 +1,287 @@
+#![allow(missing_docs)]
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use clap::{Parser, ValueEnum};
+use clap_git_options::GitOptions;
+use clap_verbosity_flag::{Verbosity, WarnLevel};
+
+use posix_errors::PosixError;
+
+use git_issue::{CacheError, DataSource, Issue};
+
+#[derive(Parser)]
+#[clap(
+    author,
+    version,
+    about = "Generate release notes from issues closed in a commit range",
+    help_expected = true,
+    dont_collapse_args_in_usage = true
+)]
+struct Args {
+    /// Commit range like v1.0..v1.1, a single revision means <revision>..HEAD
+    range: String,
+
+    /// Output format
+    #[clap(long, value_enum, default_value = "markdown")]
+    format: Format,
+
+    /// Group issues by tag or milestone, ignored by the keep-a-changelog format
+    #[clap(long, value_enum, default_value = "tag")]
+    group_by: GroupBy,
+
+    /// Release name used in the keep-a-changelog heading
+    #[clap(long, value_name = "NAME", default_value = "Unreleased")]
+    release: String,
+
+    #[clap(flatten)]
+    git: GitOptions,
+
+    #[clap(flatten, next_help_heading = "OUTPUT")]
+    verbose: Verbosity<WarnLevel>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+enum Format {
+    /// A section per group
+    Markdown,
+    /// A release section as described on https://keepachangelog.com
+    KeepAChangelog,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+enum GroupBy {
+    Tag,
+    Milestone,
+}
+
+/// Keep-a-Changelog sections in the order they are rendered
+const SECTIONS: [(&str, &[&str]); 6] = [
+    ("Added", &["feature", "enhancement", "new"]),
+    ("Changed", &[]),
+    ("Deprecated", &["deprecated", "deprecation"]),
+    ("Removed", &["removed", "removal"]),
+    ("Fixed", &["bug", "bugfix", "fix"]),
+    ("Security", &["security"]),
+];
+
+/// Return the Keep-a-Changelog section of an issue, defaults to `Changed`
+fn section(issue: &Issue<'_>) -> &'static str {
+    // Sections with a higher priority come first
+    let priority = ["Security", "Removed", "Deprecated", "Fixed", "Added"];
+    priority
+        .iter()
+        .find(|name| {
+            SECTIONS.iter().any(|(section, tags)| {
+                section == *name && tags.iter().any(|t| issue.tags().iter().any(|it| it == t))
+            })
+        })
+        .copied()
+        .unwrap_or("Changed")
+}
+
+fn entry(issue: &Issue<'_>) -> String {
+    format!("- {} ({})", issue.title(), issue.id().short_id())
+}
+
+fn markdown(issues: &[Issue<'_>], group_by: GroupBy) -> String {
+    let mut groups: BTreeMap<String, Vec<&Issue<'_>>> = BTreeMap::new();
+    let mut other = vec![];
+    for issue in issues {
+        let names: Vec<String> = match group_by {
+            GroupBy::Tag => issue
+                .tags()
+                .iter()
+                .filter(|t| *t != "open" && *t != "closed")
+                .cloned()
+                .collect(),
+            GroupBy::Milestone => issue.milestone().iter().cloned().collect(),
+        };
+        if names.is_empty() {
+            other.push(issue);
+        }
+        for name in names {
+            groups.entry(name).or_default().push(issue);
+        }
+    }
+    let other_name = match group_by {
+        GroupBy::Tag => "Other",
+        GroupBy::Milestone => "No milestone",
+    };
+    let mut sections: Vec<(String, Vec<&Issue<'_>>)> = groups.into_iter().collect();
+    if !other.is_empty() {
+        sections.push((other_name.to_owned(), other));
+    }
+    sections
+        .into_iter()
+        .map(|(name, members)| {
+            let entries: Vec<String> = members.into_iter().map(entry).collect();
+            format!("## {}\n\n{}\n", name, entries.join("\n"))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn keep_a_changelog(issues: &[Issue<'_>], release: &str, date: Option<&str>) -> String {
+    let mut result = match date {
+        Some(d) if release != "Unreleased" => format!("## [{}] - {}\n", release, d),
+        _ => format!("## [{}]\n", release),
+    };
+    for (name, _) in SECTIONS {
+        let entries: Vec<String> = issues
+            .iter()
+            .filter(|i| section(i) == name)
+            .map(entry)
+            .collect();
+        if !entries.is_empty() {
+            result.push_str(&format!("\n### {}\n\n{}\n", name, entries.join("\n")));
+        }
+    }
+    result
+}
+
+fn cache(issue: &mut Issue<'_>) -> Result<(), CacheError> {
+    issue.cache_desc()?;
+    issue.cache_tags()?;
+    issue.cache_milestone()?;
+    Ok(())
+}
+
+fn execute(args: &Args, data: &DataSource, out: &mut dyn Write) -> Result<(), PosixError> {
+    let range = if args.range.contains("..") {
+        args.range.clone()
+    } else {
+        format!("{}..HEAD", args.range)
+    };
+    let mut issues = vec![];
+    for id in data.closed_in_range(&range)? {
+        let mut issue = Issue::new(data, id);
+        match cache(&mut issue) {
+            Ok(_) => issues.push(issue),
+            Err(e) => log::warn!("{}: {}", issue.id().short_id(), e),
+        }
+    }
+
+    let text = match args.format {
+        Format::Markdown => markdown(&issues, args.group_by),
+        Format::KeepAChangelog => {
+            let to = match range.split_once("..") {
+                Some((_, to)) if !to.trim_start_matches('.').is_empty() => {
+                    to.trim_start_matches('.')
+                }
+                _ => "HEAD",
+            };
+            let date = data
+                .repo
+                .git()
+                .args(["log", "-1", "--format=%as", to, "--"])
+                .output()
+                .ok()
+                .filter(|o| o.status.success())
+                .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_owned());
+            keep_a_changelog(&issues, &args.release, date.as_deref())
+        }
+    };
+    out.write_all(text.as_bytes())?;
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(not(tarpaulin_include))]
+#[allow(clippy::exit)]
+fn main() {
+    let args = Args::parse();
+    cli_log::init_with_level(args.verbose.log_level_filter());
+    log::debug!("Log Level is set to {}", log::max_level());
+    let data = match git_issue::DataSource::try_new(&args.git) {
+        Err(e) => {
+            let err: PosixError = e.into();
+            log::error!("{}", err);
+            std::process::exit(err.code());
+        }
+        Ok(repo) => repo,
+    };
+
+    if let Err(e) = execute(&args, &data, &mut std::io::stdout().lock()) {
+        log::error!("{}", e);
+        std::process::exit(e.code());
+    }
+}
+
+#[cfg(test)]
+mod cmd_changelog {
+    use clap::Parser;
+    use git_issue::{DataSource, Id};
+
+    fn close(data: &DataSource, id: &Id) {
+        let transaction = data.transaction().unwrap();
+        data.close_issue(id).unwrap();
+        let message = format!("DONE({}): {}", id.short_id(), data.title(id).unwrap());
+        transaction.commit(&message).unwrap();
+    }
+
+    fn changelog(data: &DataSource, args: &[&str]) -> String {
+        let args = Parser::try_parse_from(args).expect("Parsed arguments");
+        let mut out: Vec<u8> = vec![];
+        crate::execute(&args, data, &mut out).expect("Generated changelog");
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn closed_in_range() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let tmp = tmp_dir.path();
+        git_wrapper::setup_test_author();
+        git_issue::create(tmp, false).unwrap();
+        let data = DataSource::try_from(tmp).unwrap();
+        let early = data.create_issue("Early", vec![], None).unwrap();
+        close(&data, &early);
+        let start = data.repo.head();
+
+        let bug = data
+            .create_issue("Crash", vec!["bug".to_owned()], Some("v1".to_owned()))
+            .unwrap();
+        let feature = data
+            .create_issue("Export", vec!["feature".to_owned()], None)
+            .unwrap();
+        data.create_issue("Still open", vec![], None).unwrap();
+        close(&data, &bug);
+        close(&data, &feature);
+
+        let range = format!("{}..HEAD", start);
+        let markdown = changelog(&data, &["git-issue-changelog", &range]);
+        let expected = format!(
+            "## bug\n\n- Crash ({})\n\n## feature\n\n- Export ({})\n",
+            bug.short_id(),
+            feature.short_id()
+        );
+        assert_eq!(markdown, expected);
+
+        let by_milestone = changelog(
+            &data,
+            &["git-issue-changelog", &start, "--group-by", "milestone"],
+        );
+        let expected2 = format!(
+            "## v1\n\n- Crash ({})\n\n## No milestone\n\n- Export ({})\n",
+            bug.short_id(),
+            feature.short_id()
+        );
+        assert_eq!(by_milestone, expected2);
+
+        let keep = changelog(
+            &data,
+            &[
+                "git-issue-changelog",
+                &range,
+                "--format",
+                "keep-a-changelog",
+            ],
+        );
+        let expected3 = format!(
+            "## [Unreleased]\n\n### Added\n\n- Export ({})\n\n### Fixed\n\n- Crash ({})\n",
+            feature.short_id(),
+            bug.short_id()
+        );
+        assert_eq!(keep, expected3);
+    }
+}
@@ -4,7 +4,7 @@ use clap_git_options::GitOptions;
 use clap_verbosity_flag::{Verbosity, WarnLevel};
 use posix_errors::PosixError;
 
-use git_issue::{DataSource, FindError, Id, WriteResult};
+use git_issue::{DataSource, FindError, Id, MergeStrategy, WriteResult};
 
 #[derive(Parser)]
 #[clap(
@@ -18,6 +18,22 @@ struct Args {
     #[clap(long_help = "Issue id", required = true)]
     issue_ids: Vec<String>,
 
+    #[clap(
+        long,
+        value_enum,
+        value_name = "STRATEGY",
+        long_help = "How the issue changes are committed, overrides issues.strategy in .issues/config"
+    )]
+    strategy: Option<MergeStrategy>,
+
+    #[clap(
+        long = "ref",
+        value_name = "REF",
+        long_help = "Commit the issue changes to this reference without touching the working \
+                     tree, e.g. refs/heads/issues, overrides issues.ref in .issues/config"
+    )]
+    reference: Option<String>,
+
     #[clap(flatten, next_help_heading = "Output")]
     verbose: Verbosity<WarnLevel>,
 
@@ -45,7 +61,15 @@ fn close_issues(data: &DataSource, ids: &[Id]) -> Result<WriteResult, PosixError
     Ok(WriteResult::from(results))
 }
 
-fn execute(args: &Args, mut data: DataSource) -> Result<(), PosixError> {
+fn execute(args: &Args, data: DataSource) -> Result<(), PosixError> {
+    let data = match args.strategy {
+        Some(strategy) => data.with_strategy(strategy),
+        None => data,
+    };
+    let data = match &args.reference {
+        Some(reference) => data.with_ref(reference)?,
+        None => data,
+    };
     let issue_ids: Vec<Id> = args
         .issue_ids
         .iter()
@@ -54,7 +78,7 @@ fn execute(args: &Args, mut data: DataSource) -> Result<(), PosixError> {
         .map_err(PosixError::from)?;
 
     log::info!("Starting transaction");
-    data.start_transaction().map_err(PosixError::from)?;
+    let transaction = data.transaction().map_err(PosixError::from)?;
 
     match close_issues(&data, &issue_ids) {
         Ok(WriteResult::Applied) => {
@@ -73,16 +97,16 @@ fn execute(args: &Args, mut data: DataSource) -> Result<(), PosixError> {
                 format!("gi: Closed {}", text)
             };
             log::info!("Committing transaction");
-            data.finish_transaction(&msg).map_err(PosixError::from)
+            transaction.commit(&msg).map_err(PosixError::from)
         }
         Ok(WriteResult::NoChanges) => {
             log::warn!("Nothing to do");
             log::info!("Rolling back transaction");
-            data.rollback_transaction().map_err(PosixError::from)
+            transaction.rollback().map_err(PosixError::from)
         }
         Err(e) => {
             log::warn!("An error happend. Rolling back transaction.");
-            data.rollback_transaction()?;
+            transaction.rollback()?;
             Err(e)
         }
     }
@@ -0,0 +1,236 @@
+#![allow(missing_docs)]
+use std::io::Write;
+
+use clap::Parser;
+use clap_git_options::GitOptions;
+use clap_verbosity_flag::{Verbosity, WarnLevel};
+use time::{Duration, OffsetDateTime, UtcOffset};
+
+use posix_errors::PosixError;
+
+use git_issue::{CacheError, ColorChoice, DataSource, DateFormat, FormatString, Issue};
+
+#[derive(Parser)]
+#[clap(
+    author,
+    version,
+    about = "List overdue & upcoming issues, exits with 1 if any issue is overdue",
+    help_expected = true,
+    dont_collapse_args_in_usage = true
+)]
+struct Args {
+    /// Include issues due within the given time, e.g. 7d or 2w
+    #[clap(
+        long,
+        value_name = "DURATION",
+        default_value = "7d",
+        value_parser = git_issue::parse_duration
+    )]
+    within: Duration,
+
+    /// Format string
+    #[clap(
+        short = 'l',
+        help_heading = "OUTPUT",
+        default_value = "%i %d %D",
+        value_parser=FormatString::try_new,
+    )]
+    format_string: FormatString,
+
+    /// When to colour the output
+    #[clap(
+        long,
+        value_enum,
+        value_name = "WHEN",
+        default_value = "auto",
+        help_heading = "OUTPUT"
+    )]
+    color: ColorChoice,
+
+    /// Date format: default, iso, iso-strict, rfc, short, relative, unix or format:<strftime>.
+    /// Append -local to use the local timezone
+    #[clap(
+        long,
+        value_name = "FORMAT",
+        default_value = "short",
+        help_heading = "OUTPUT"
+    )]
+    date: DateFormat,
+
+    #[clap(flatten)]
+    git: GitOptions,
+
+    #[clap(flatten, next_help_heading = "OUTPUT")]
+    verbose: Verbosity<WarnLevel>,
+}
+
+/// Open issues with a due date, grouped by urgency
+#[derive(Default)]
+struct Report<'src> {
+    overdue: Vec<Issue<'src>>,
+    today: Vec<Issue<'src>>,
+    upcoming: Vec<Issue<'src>>,
+}
+
+impl<'src> Report<'src> {
+    fn new(issues: Vec<Issue<'src>>, now: OffsetDateTime, within: Duration) -> Self {
+        let offset = UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC);
+        let today = now.to_offset(offset).date();
+        let mut result = Self::default();
+        for issue in issues {
+            let due = match issue.ddate() {
+                Some(d) if !issue.is_closed() => *d,
+                _ => continue,
+            };
+            if due < now {
+                result.overdue.push(issue);
+            } else if due.to_offset(offset).date() == today {
+                result.today.push(issue);
+            } else if due <= now.saturating_add(within) {
+                result.upcoming.push(issue);
+            }
+        }
+        for group in [&mut result.overdue, &mut result.today, &mut result.upcoming] {
+            group.sort_by(|a, b| {
+                a.ddate()
+                    .cmp(b.ddate())
+                    .then_with(|| a.id().id().cmp(b.id().id()))
+            });
+        }
+        result
+    }
+}
+
+fn cache(issue: &mut Issue<'_>) -> Result<(), CacheError> {
+    issue.cache_tags()?;
+    issue.cache_ddate()?;
+    Ok(())
+}
+
+fn execute(args: &Args, data: &DataSource, out: &mut dyn Write) -> Result<(), PosixError> {
+    let mut issues: Vec<Issue<'_>> = vec![];
+    for result in data.all() {
+        match result {
+            Ok(mut issue) => match cache(&mut issue) {
+                Ok(_) => issues.push(issue),
+                Err(e) => log::warn!("{}: {}", issue.id().short_id(), e),
+            },
+            Err(e) => log::warn!("{}", e),
+        }
+    }
+
+    let format = args
+        .format_string
+        .clone()
+        .with_color(args.color.enabled())
+        .with_date_format(args.date.clone());
+    let mut report = Report::new(issues, OffsetDateTime::now_utc(), args.within);
+    let upcoming = format!("Due within {}", git_issue::format_duration(args.within));
+    let mut first = true;
+    for (title, group) in [
+        ("Overdue", &mut report.overdue),
+        ("Due today", &mut report.today),
+        (upcoming.as_str(), &mut report.upcoming),
+    ] {
+        if group.is_empty() {
+            continue;
+        }
+        if !first {
+            writeln!(out)?;
+        }
+        first = false;
+        writeln!(out, "{} ({})", title, group.len())?;
+        for issue in group.iter_mut() {
+            writeln!(out, "{}", format.format(issue))?;
+        }
+    }
+    out.flush()?;
+
+    match report.overdue.len() {
+        0 => Ok(()),
+        1 => Err(PosixError::new(1, "1 issue is overdue".to_owned())),
+        n => Err(PosixError::new(1, format!("{} issues are overdue", n))),
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+#[allow(clippy::exit)]
+fn main() {
+    let args = Args::parse();
+    cli_log::init_with_level(args.verbose.log_level_filter());
+    log::debug!("Log Level is set to {}", log::max_level());
+    let data = match git_issue::DataSource::try_new(&args.git) {
+        Err(e) => {
+            let err: PosixError = e.into();
+            log::error!("{}", err);
+            std::process::exit(err.code());
+        }
+        Ok(repo) => repo,
+    };
+
+    if let Err(e) = execute(&args, &data, &mut std::io::stdout().lock()) {
+        log::error!("{}", e);
+        std::process::exit(e.code());
+    }
+}
+
+#[cfg(test)]
+mod cmd_due {
+    use clap::Parser;
+    use git_issue::DataSource;
+    use time::format_description::well_known::Rfc3339;
+    use time::{Duration, OffsetDateTime};
+
+    fn set_due(data: &DataSource, title: &str, offset: Duration) -> git_issue::Id {
+        let id = data.create_issue(title, vec![], None).unwrap();
+        let due = OffsetDateTime::now_utc() + offset;
+        std::fs::write(
+            id.path(&data.issues_dir).join("duedate"),
+            due.format(&Rfc3339).unwrap(),
+        )
+        .unwrap();
+        id
+    }
+
+    fn due(data: &DataSource) -> (String, Option<i32>) {
+        let args = Parser::try_parse_from(["git-issue-due", "-l", "%D"]).unwrap();
+        let mut out: Vec<u8> = vec![];
+        let code = crate::execute(&args, data, &mut out)
+            .err()
+            .map(|e| e.code());
+        (String::from_utf8(out).unwrap(), code)
+    }
+
+    #[test]
+    fn nothing_overdue() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let tmp = tmp_dir.path();
+        git_wrapper::setup_test_author();
+        git_issue::create(tmp, false).unwrap();
+        let data = DataSource::try_from(tmp).unwrap();
+        set_due(&data, "Later", Duration::days(5));
+        set_due(&data, "Soon", Duration::days(2));
+        set_due(&data, "Next month", Duration::days(30));
+
+        let (actual, code) = due(&data);
+        assert_eq!(actual, "Due within 7 days (2)\nSoon\nLater\n");
+        assert_eq!(code, None);
+    }
+
+    #[test]
+    fn overdue() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let tmp = tmp_dir.path();
+        git_wrapper::setup_test_author();
+        git_issue::create(tmp, false).unwrap();
+        let data = DataSource::try_from(tmp).unwrap();
+        set_due(&data, "Yesterday", Duration::days(-1));
+        set_due(&data, "Last week", Duration::days(-7));
+        let closed = set_due(&data, "Closed", Duration::days(-3));
+        data.close_issue(&closed).unwrap();
+
+        let (actual, code) = due(&data);
+        assert_eq!(actual, "Overdue (2)\nLast week\nYesterday\n");
+        assert_eq!(code, Some(1));
+    }
+}
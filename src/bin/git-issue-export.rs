@@ -0,0 +1,593 @@
+#![allow(missing_docs)]
+use std::borrow::Cow;
+use std::io::Write;
+use std::path::PathBuf;
+
+use clap::{ArgGroup, Parser, ValueEnum};
+use clap_git_options::GitOptions;
+use clap_verbosity_flag::{Verbosity, WarnLevel};
+use time::format_description::well_known::Rfc3339;
+use time::{OffsetDateTime, UtcOffset};
+
+use posix_errors::PosixError;
+
+use git_issue::{CacheError, DataSource, Issue};
+
+#[derive(Parser)]
+#[clap(
+    author,
+    version,
+    about = "Export issues",
+    help_expected = true,
+    dont_collapse_args_in_usage = true,
+    group(ArgGroup::new("format").required(true).args(&["csv", "tsv", "ical"]))
+)]
+struct Args {
+    /// Export as comma separated values (RFC 4180)
+    #[clap(long, help_heading = "FORMAT")]
+    csv: bool,
+
+    /// Export as tab separated values
+    #[clap(long, help_heading = "FORMAT")]
+    tsv: bool,
+
+    /// Export issues with a due date as iCalendar to-dos and milestone due dates as events
+    #[clap(long, help_heading = "FORMAT")]
+    ical: bool,
+
+    /// Columns to export
+    #[clap(
+        short,
+        long,
+        value_enum,
+        value_delimiter = ',',
+        default_value = "id,title,tags,milestone,cdate,ddate",
+        help_heading = "FORMAT"
+    )]
+    columns: Vec<Column>,
+
+    /// Print a header row with the column names
+    #[clap(long, help_heading = "FORMAT")]
+    header: bool,
+
+    /// Separator used between multiple tags in a single cell
+    #[clap(long, default_value = ";", help_heading = "FORMAT")]
+    tag_separator: String,
+
+    /// Export open & closed issues
+    #[clap(short, long)]
+    all: bool,
+
+    /// Write to file instead of stdout
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+
+    #[clap(flatten)]
+    git: GitOptions,
+
+    #[clap(flatten, next_help_heading = "OUTPUT")]
+    verbose: Verbosity<WarnLevel>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+enum Column {
+    Id,
+    Title,
+    Tags,
+    Milestone,
+    Cdate,
+    Ddate,
+}
+
+impl Column {
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Id => "id",
+            Self::Title => "title",
+            Self::Tags => "tags",
+            Self::Milestone => "milestone",
+            Self::Cdate => "cdate",
+            Self::Ddate => "ddate",
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+enum Separated {
+    Comma,
+    Tab,
+}
+
+impl Separated {
+    const fn delimiter(self) -> char {
+        match self {
+            Self::Comma => ',',
+            Self::Tab => '\t',
+        }
+    }
+
+    const fn terminator(self) -> &'static str {
+        match self {
+            Self::Comma => "\r\n",
+            Self::Tab => "\n",
+        }
+    }
+
+    /// Escape a single cell value
+    ///
+    /// CSV fields containing a delimiter, a quote or a line break are quoted and quotes are
+    /// doubled. TSV has no quoting, so tabs, line breaks and backslashes are backslash escaped.
+    fn escape(self, value: &str) -> Cow<'_, str> {
+        match self {
+            Self::Comma => {
+                if value.contains(&[',', '"', '\r', '\n'][..]) {
+                    Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+                } else {
+                    Cow::Borrowed(value)
+                }
+            }
+            Self::Tab => {
+                if value.contains(&['\\', '\t', '\r', '\n'][..]) {
+                    Cow::Owned(
+                        value
+                            .replace('\\', "\\\\")
+                            .replace('\t', "\\t")
+                            .replace('\r', "\\r")
+                            .replace('\n', "\\n"),
+                    )
+                } else {
+                    Cow::Borrowed(value)
+                }
+            }
+        }
+    }
+
+    fn record(self, cells: &[String]) -> String {
+        let mut result = cells
+            .iter()
+            .map(|c| self.escape(c))
+            .collect::<Vec<_>>()
+            .join(&self.delimiter().to_string());
+        result.push_str(self.terminator());
+        result
+    }
+}
+
+/// Columns needed for iCalendar export
+const ICAL_COLUMNS: [Column; 4] = [Column::Title, Column::Tags, Column::Cdate, Column::Ddate];
+
+/// Escape an iCalendar TEXT value (RFC 5545 3.3.11)
+fn ical_escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Return a content line folded after 75 octets (RFC 5545 3.1)
+fn ical_line(line: &str) -> String {
+    let mut result = String::new();
+    let mut octets = 0;
+    for c in line.chars() {
+        let len = c.len_utf8();
+        // arithmetic: octets never exceeds 75 + 4
+        #[allow(clippy::arithmetic_side_effects)]
+        if octets + len > 75 {
+            result.push_str("\r\n ");
+            octets = 1;
+        }
+        result.push(c);
+        octets = octets.saturating_add(len);
+    }
+    result.push_str("\r\n");
+    result
+}
+
+/// Format a date as UTC iCalendar DATE-TIME
+fn ical_date_time(date: &OffsetDateTime) -> String {
+    let utc = date.to_offset(UtcOffset::UTC);
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        utc.year(),
+        u8::from(utc.month()),
+        utc.day(),
+        utc.hour(),
+        utc.minute(),
+        utc.second()
+    )
+}
+
+fn ical(
+    issues: &[Issue<'_>],
+    milestones: &[(String, OffsetDateTime)],
+    now: &OffsetDateTime,
+) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_owned(),
+        "VERSION:2.0".to_owned(),
+        format!(
+            "PRODID:-//git-issue//git-issue {}//EN",
+            env!("CARGO_PKG_VERSION")
+        ),
+    ];
+    let stamp = ical_date_time(now);
+    for issue in issues {
+        let ddate = match issue.ddate() {
+            Some(d) => d,
+            None => continue,
+        };
+        lines.push("BEGIN:VTODO".to_owned());
+        lines.push(format!("UID:{}@git-issue", issue.id().id()));
+        lines.push(format!("DTSTAMP:{}", stamp));
+        lines.push(format!("CREATED:{}", ical_date_time(issue.cdate())));
+        lines.push(format!("DUE:{}", ical_date_time(ddate)));
+        lines.push(format!("SUMMARY:{}", ical_escape(&issue.title())));
+        let status = if issue.is_closed() {
+            "COMPLETED"
+        } else {
+            "NEEDS-ACTION"
+        };
+        lines.push(format!("STATUS:{}", status));
+        let categories: Vec<String> = issue
+            .tags()
+            .iter()
+            .filter(|t| *t != "open" && *t != "closed")
+            .map(|t| ical_escape(t))
+            .collect();
+        if !categories.is_empty() {
+            lines.push(format!("CATEGORIES:{}", categories.join(",")));
+        }
+        lines.push("END:VTODO".to_owned());
+    }
+    for (name, due) in milestones {
+        let date = due.to_offset(UtcOffset::UTC).date();
+        lines.push("BEGIN:VEVENT".to_owned());
+        lines.push(format!("UID:milestone-{}@git-issue", ical_escape(name)));
+        lines.push(format!("DTSTAMP:{}", stamp));
+        lines.push(format!(
+            "DTSTART;VALUE=DATE:{:04}{:02}{:02}",
+            date.year(),
+            u8::from(date.month()),
+            date.day()
+        ));
+        lines.push(format!("SUMMARY:Milestone {} due", ical_escape(name)));
+        lines.push("END:VEVENT".to_owned());
+    }
+    lines.push("END:VCALENDAR".to_owned());
+    lines.iter().map(|l| ical_line(l)).collect()
+}
+
+fn cache(issue: &mut Issue<'_>, columns: &[Column]) -> Result<(), CacheError> {
+    issue.cache_tags()?;
+    for column in columns {
+        match column {
+            Column::Id => {}
+            Column::Title => {
+                issue.cache_desc()?;
+            }
+            Column::Tags => {
+                issue.cache_tags()?;
+            }
+            Column::Milestone => {
+                issue.cache_milestone()?;
+            }
+            Column::Cdate => {
+                issue.cache_cdate()?;
+            }
+            Column::Ddate => {
+                issue.cache_ddate()?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn cell(issue: &Issue<'_>, column: Column, tag_separator: &str) -> String {
+    match column {
+        Column::Id => issue.id().id().clone(),
+        Column::Title => issue.title(),
+        Column::Tags => issue.tags().join(tag_separator),
+        Column::Milestone => issue.milestone().clone().unwrap_or_default(),
+        Column::Cdate => issue.cdate().format(&Rfc3339).unwrap_or_default(),
+        Column::Ddate => issue
+            .ddate()
+            .and_then(|d| d.format(&Rfc3339).ok())
+            .unwrap_or_default(),
+    }
+}
+
+fn execute(args: &Args, data: &DataSource, out: &mut dyn Write) -> Result<(), PosixError> {
+    let mut error = false;
+    let columns: &[Column] = if args.ical {
+        &ICAL_COLUMNS
+    } else {
+        &args.columns
+    };
+
+    let mut issues: Vec<Issue<'_>> = {
+        let (success, errors): (Vec<_>, Vec<_>) = data.all().partition(Result::is_ok);
+        for e in errors.into_iter().map(Result::unwrap_err) {
+            error = true;
+            log::warn!("{}", e);
+        }
+        success.into_iter().map(Result::unwrap).collect()
+    };
+    issues.retain_mut(|issue| match cache(issue, columns) {
+        Ok(_) => args.all || !issue.is_closed(),
+        Err(e) => {
+            error = true;
+            log::warn!("{}: {}", issue.id().short_id(), e);
+            false
+        }
+    });
+    issues.sort_by(|a, b| a.id().id().cmp(b.id().id()));
+
+    if args.ical {
+        let calendar = ical(
+            &issues,
+            &data.milestone_duedates(),
+            &OffsetDateTime::now_utc(),
+        );
+        out.write_all(calendar.as_bytes())?;
+        out.flush()?;
+        return if error {
+            Err(PosixError::new(1, "Errors happened".to_owned()))
+        } else {
+            Ok(())
+        };
+    }
+
+    let format = if args.tsv {
+        Separated::Tab
+    } else {
+        Separated::Comma
+    };
+    if args.header {
+        let names: Vec<String> = args.columns.iter().map(|c| c.name().to_owned()).collect();
+        out.write_all(format.record(&names).as_bytes())?;
+    }
+    for issue in &issues {
+        let cells: Vec<String> = args
+            .columns
+            .iter()
+            .map(|c| cell(issue, *c, &args.tag_separator))
+            .collect();
+        out.write_all(format.record(&cells).as_bytes())?;
+    }
+    out.flush()?;
+
+    if error {
+        Err(PosixError::new(1, "Errors happened".to_owned()))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+#[allow(clippy::exit)]
+fn main() {
+    let args = Args::parse();
+    cli_log::init_with_level(args.verbose.log_level_filter());
+    log::debug!("Log Level is set to {}", log::max_level());
+    let data = match git_issue::DataSource::try_new(&args.git) {
+        Err(e) => {
+            let err: PosixError = e.into();
+            log::error!("{}", err);
+            std::process::exit(err.code());
+        }
+        Ok(repo) => repo,
+    };
+
+    let result = match &args.output {
+        Some(path) => std::fs::File::create(path)
+            .map_err(PosixError::from)
+            .and_then(|mut file| execute(&args, &data, &mut file)),
+        None => execute(&args, &data, &mut std::io::stdout().lock()),
+    };
+    if let Err(e) = result {
+        log::error!("{}", e);
+        std::process::exit(e.code());
+    }
+}
+
+#[cfg(test)]
+mod escape {
+    use crate::Separated;
+
+    #[test]
+    fn csv_plain() {
+        assert_eq!(Separated::Comma.escape("Foo Bar"), "Foo Bar");
+    }
+
+    #[test]
+    fn csv_separator() {
+        assert_eq!(Separated::Comma.escape("Foo, Bar"), "\"Foo, Bar\"");
+    }
+
+    #[test]
+    fn csv_quotes() {
+        assert_eq!(
+            Separated::Comma.escape("Say \"Hello\""),
+            "\"Say \"\"Hello\"\"\""
+        );
+    }
+
+    #[test]
+    fn csv_line_break() {
+        assert_eq!(Separated::Comma.escape("Foo\nBar"), "\"Foo\nBar\"");
+    }
+
+    #[test]
+    fn tsv() {
+        assert_eq!(Separated::Tab.escape("Foo, Bar"), "Foo, Bar");
+        assert_eq!(Separated::Tab.escape("Foo\tBar\\"), "Foo\\tBar\\\\");
+        assert_eq!(Separated::Tab.escape("Foo\nBar"), "Foo\\nBar");
+    }
+
+    #[test]
+    fn ical_text() {
+        assert_eq!(
+            crate::ical_escape("Foo, Bar; Baz\\\nQux"),
+            "Foo\\, Bar\\; Baz\\\\\\nQux"
+        );
+    }
+
+    #[test]
+    fn ical_folding() {
+        let line = format!("SUMMARY:{}", "x".repeat(100));
+        let folded = crate::ical_line(&line);
+        let lines: Vec<&str> = folded.split("\r\n").collect();
+        assert_eq!(lines.len(), 3, "Two content lines & an empty remainder");
+        assert_eq!(lines[0].len(), 75);
+        assert!(lines[1].starts_with(' '));
+        assert_eq!(lines[0].len() + lines[1].len() - 1, line.len());
+    }
+}
+
+#[cfg(test)]
+mod cmd_export {
+    use clap::Parser;
+    use git_issue::DataSource;
+
+    fn export(data: &DataSource, args: &[&str]) -> String {
+        let args = Parser::try_parse_from(args).expect("Parsed arguments");
+        let mut out: Vec<u8> = vec![];
+        crate::execute(&args, data, &mut out).expect("Exported issues");
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn csv_with_header() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let tmp = tmp_dir.path();
+        git_wrapper::setup_test_author();
+        git_issue::create(tmp, false).unwrap();
+        let data = DataSource::try_from(tmp).unwrap();
+        let id = data
+            .create_issue(
+                "Foo, \"Bar\"",
+                vec!["bug".to_owned()],
+                Some("v1.0".to_owned()),
+            )
+            .unwrap();
+
+        let actual = export(
+            &data,
+            &[
+                "git-issue-export",
+                "--csv",
+                "--header",
+                "-c",
+                "id,title,tags,milestone",
+            ],
+        );
+        let expected = format!(
+            "id,title,tags,milestone\r\n{},\"Foo, \"\"Bar\"\"\",bug;open,v1.0\r\n",
+            id.id()
+        );
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn tsv_skips_closed() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let tmp = tmp_dir.path();
+        git_wrapper::setup_test_author();
+        git_issue::create(tmp, false).unwrap();
+        let data = DataSource::try_from(tmp).unwrap();
+        let open = data.create_issue("Open\tissue", vec![], None).unwrap();
+        let closed = data.create_issue("Closed issue", vec![], None).unwrap();
+        data.close_issue(&closed).unwrap();
+
+        {
+            let actual = export(&data, &["git-issue-export", "--tsv", "-c", "id,title"]);
+            let expected = format!("{}\tOpen\\tissue\n", open.id());
+            assert_eq!(actual, expected);
+        }
+        {
+            let actual = export(&data, &["git-issue-export", "--tsv", "-a", "-c", "id"]);
+            assert_eq!(actual.lines().count(), 2, "Exported open & closed issues");
+        }
+    }
+}
+
+#[cfg(test)]
+mod cmd_export_ical {
+    use clap::Parser;
+    use git_issue::DataSource;
+
+    #[test]
+    fn todos_and_events() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let tmp = tmp_dir.path();
+        git_wrapper::setup_test_author();
+        git_issue::create(tmp, false).unwrap();
+        let data = DataSource::try_from(tmp).unwrap();
+        let due = data
+            .create_issue("Release, finally", vec!["bug".to_owned()], None)
+            .unwrap();
+        data.create_issue("No due date", vec![], None).unwrap();
+        std::fs::write(
+            due.path(&data.issues_dir).join("duedate"),
+            "2022-10-20T12:00:00+02:00\n",
+        )
+        .unwrap();
+        std::fs::write(
+            data.issues_dir.join("config"),
+            "[milestone \"v1.0\"]\n\tduedate = 2022-10-31\n",
+        )
+        .unwrap();
+
+        let args = Parser::try_parse_from(["git-issue-export", "--ical"]).unwrap();
+        let mut out: Vec<u8> = vec![];
+        crate::execute(&args, &data, &mut out).unwrap();
+        let actual = String::from_utf8(out).unwrap();
+
+        assert!(actual.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(actual.ends_with("END:VCALENDAR\r\n"));
+        assert_eq!(actual.matches("BEGIN:VTODO").count(), 1);
+        assert!(actual.contains(&format!("UID:{}@git-issue\r\n", due.id())));
+        assert!(actual.contains("DUE:20221020T100000Z\r\n"));
+        assert!(actual.contains("SUMMARY:Release\\, finally\r\n"));
+        assert!(actual.contains("STATUS:NEEDS-ACTION\r\n"));
+        assert!(actual.contains("CATEGORIES:bug\r\n"));
+        assert!(actual.contains("BEGIN:VEVENT\r\nUID:milestone-v1.0@git-issue\r\n"));
+        assert!(actual.contains("DTSTART;VALUE=DATE:20221031\r\n"));
+    }
+}
+
+#[cfg(test)]
+mod parse_args {
+    use crate::{Args, Column};
+    use clap::Parser;
+
+    #[test]
+    fn no_format() {
+        let result: Result<Args, _> = Parser::try_parse_from(["git-issue-export"]);
+        assert!(result.is_err(), "Expected a format flag");
+    }
+
+    #[test]
+    fn ical() {
+        let result: Result<Args, _> =
+            Parser::try_parse_from(["git-issue-export", "--ical", "--csv"]);
+        assert!(result.is_err(), "Formats are mutually exclusive");
+    }
+
+    #[test]
+    fn both_formats() {
+        let result: Result<Args, _> =
+            Parser::try_parse_from(["git-issue-export", "--csv", "--tsv"]);
+        assert!(result.is_err(), "Formats are mutually exclusive");
+    }
+
+    #[test]
+    fn columns() {
+        let args: Args =
+            Parser::try_parse_from(["git-issue-export", "--csv", "--columns", "title,ddate"])
+                .expect("Parsed columns");
+        assert_eq!(args.columns, vec![Column::Title, Column::Ddate]);
+    }
+}
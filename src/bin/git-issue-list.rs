@@ -1,12 +1,22 @@
 #![allow(missing_docs)]
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::str::FromStr;
+
 use clap::Parser;
-use clap::ValueEnum;
 use clap_git_options::GitOptions;
 use clap_verbosity_flag::{Verbosity, WarnLevel};
+use git_issue::par_map;
 use git_issue::CacheError;
+use git_issue::ColorChoice;
+use git_issue::DateFormat;
 use git_issue::FormatString;
 use git_issue::Issue;
+use git_issue::IssueLoadError;
+use git_issue::Output;
 use posix_errors::PosixError;
+use time::{Duration, OffsetDateTime};
 
 use git_issue::DataSource;
 
@@ -29,13 +39,21 @@ struct FilterArgs {
         name = "milestone",
         short = 'm',
         long,
-        conflicts_with = "without-milestone"
+        conflicts_with = "without_milestone"
     )]
     with_milestone: Option<String>,
 
     /// Include issues *without* any milestone
     #[clap(short = 'M', long)]
     without_milestone: bool,
+
+    /// Include issues past their due date
+    #[clap(long)]
+    overdue: bool,
+
+    /// Include issues due within the given time, e.g. 7d or 2w
+    #[clap(long, value_name = "DURATION", value_parser = git_issue::parse_duration)]
+    due_within: Option<Duration>,
 }
 
 #[derive(Parser)]
@@ -47,6 +65,18 @@ struct FilterArgs {
     dont_collapse_args_in_usage = true
 )]
 struct Args {
+    /// Show the issues as of this revision, e.g. a tag or another branch
+    #[clap(long, value_name = "REV")]
+    at: Option<String>,
+
+    /// Read all issue files instead of using the metadata cache in the git directory
+    #[clap(long)]
+    no_cache: bool,
+
+    /// Fail if any issue can not be loaded, instead of skipping it with a warning
+    #[clap(long)]
+    strict: bool,
+
     #[clap(flatten)]
     git: GitOptions,
 
@@ -65,25 +95,260 @@ struct Args {
     )]
     format_string: FormatString,
 
+    /// When to colour the output
+    #[clap(
+        long,
+        value_enum,
+        value_name = "WHEN",
+        default_value = "auto",
+        help_heading = "OUTPUT"
+    )]
+    color: ColorChoice,
+
+    /// Date format: default, iso, iso-strict, rfc, short, relative, unix or format:<strftime>.
+    /// Append -local to use the local timezone
+    #[clap(
+        long,
+        value_name = "FORMAT",
+        default_value = "default",
+        help_heading = "OUTPUT"
+    )]
+    date: DateFormat,
+
+    /// Do not pipe the output into a pager
+    #[clap(long, help_heading = "OUTPUT")]
+    no_pager: bool,
+
+    /// Show at most N issues
+    #[clap(short = 'n', long, value_name = "N", help_heading = "OUTPUT")]
+    limit: Option<usize>,
+
+    /// Skip the first N issues
+    #[clap(long, value_name = "N", default_value = "0", help_heading = "OUTPUT")]
+    skip: usize,
+
     /// Print results in reverse order
     #[clap(short, long, help_heading = "ORDER OPTIONS")]
     reverse: bool,
 
-    /// Order issues by specified fields
-    #[clap(value_enum, short, long, help_heading = "ORDER OPTIONS")]
-    order: Option<SortKey>,
+    /// Order issues by comma separated keys: %c, %d, %D, %M, %m, %N, tag:<name> or field:<name>.
+    /// Prefix a key with - for descending order. Issues missing a value are listed last
+    #[clap(
+        short,
+        long,
+        value_delimiter = ',',
+        value_name = "KEYS",
+        help_heading = "ORDER OPTIONS"
+    )]
+    order: Vec<SortSpec>,
+
+    /// Group issues by milestone, tag, assignee or field:<name>
+    #[clap(long, value_name = "KEY", help_heading = "GROUPING")]
+    group_by: Option<GroupKey>,
+
+    /// Print only the number of issues per tag & milestone, or per group if --group-by is used
+    #[clap(long, visible_alias = "facets", help_heading = "GROUPING")]
+    count: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum GroupKey {
+    Field(String),
+    Milestone,
+    Tag,
+}
+
+impl FromStr for GroupKey {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "milestone" => Ok(Self::Milestone),
+            "tag" => Ok(Self::Tag),
+            "assignee" => Ok(Self::Field("assignee".to_owned())),
+            k => match k.strip_prefix("field:") {
+                Some(name) if name.is_empty() || name.contains('/') || name.starts_with('.') => {
+                    Err(format!("Invalid field name “{}”", name))
+                }
+                Some(name) => Ok(Self::Field(name.to_owned())),
+                None => Err(format!("Unknown group key “{}”", k)),
+            },
+        }
+    }
+}
+
+impl GroupKey {
+    fn cache(&self, issue: &mut Issue<'_>) -> Result<(), CacheError> {
+        match self {
+            Self::Field(name) => {
+                issue.cache_field(name)?;
+            }
+            Self::Milestone => {
+                issue.cache_milestone()?;
+            }
+            Self::Tag => {
+                issue.cache_tags()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn values(&self, issue: &Issue<'_>) -> Vec<String> {
+        match self {
+            Self::Field(name) => issue.field(name).into_iter().cloned().collect(),
+            Self::Milestone => issue.milestone().iter().cloned().collect(),
+            Self::Tag => issue.tags().clone(),
+        }
+    }
+
+    fn name(&self) -> &str {
+        match self {
+            Self::Field(name) => name,
+            Self::Milestone => "milestone",
+            Self::Tag => "tag",
+        }
+    }
+
+    /// Header of the group containing issues without a value
+    fn missing(&self) -> String {
+        format!("(no {})", self.name())
+    }
+}
+
+/// Group issue indices by value, sorted by name, issues without a value are grouped last.
+///
+/// With [`GroupKey::Tag`] an issue is part of every group of its tags.
+fn group(issues: &[Issue<'_>], key: &GroupKey) -> Vec<(String, Vec<usize>)> {
+    let mut named: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    let mut missing = vec![];
+    for (index, issue) in issues.iter().enumerate() {
+        let values = key.values(issue);
+        if values.is_empty() {
+            missing.push(index);
+        }
+        for value in values {
+            named.entry(value).or_default().push(index);
+        }
+    }
+    let mut result: Vec<(String, Vec<usize>)> = named.into_iter().collect();
+    if !missing.is_empty() {
+        result.push((key.missing(), missing));
+    }
+    result
 }
 
-#[derive(Copy, Clone, ValueEnum)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 enum SortKey {
-    #[clap(name = "%c")]
+    CommentCount,
     CreationDate,
-    #[clap(name = "%d")]
     DueDate,
-    #[clap(name = "%D")]
     Description,
-    #[clap(name = "%M")]
+    Field(String),
     Milestone,
+    ModificationDate,
+    Tag(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct SortSpec {
+    key: SortKey,
+    descending: bool,
+}
+
+impl FromStr for SortSpec {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (descending, key) = match value.strip_prefix('-') {
+            Some(key) => (true, key),
+            None => (false, value),
+        };
+        let key = match key {
+            "%c" => SortKey::CreationDate,
+            "%d" => SortKey::DueDate,
+            "%D" => SortKey::Description,
+            "%M" => SortKey::Milestone,
+            "%m" => SortKey::ModificationDate,
+            "%N" => SortKey::CommentCount,
+            k => {
+                if let Some(tag) = k.strip_prefix("tag:") {
+                    SortKey::Tag(tag.to_owned())
+                } else if let Some(name) = k.strip_prefix("field:") {
+                    if name.is_empty() || name.contains('/') || name.starts_with('.') {
+                        return Err(format!("Invalid field name “{}”", name));
+                    }
+                    SortKey::Field(name.to_owned())
+                } else {
+                    return Err(format!("Unknown sort key “{}”", k));
+                }
+            }
+        };
+        Ok(Self { key, descending })
+    }
+}
+
+impl SortSpec {
+    fn cache(&self, issue: &mut Issue<'_>) -> Result<(), CacheError> {
+        match &self.key {
+            SortKey::CommentCount => {
+                issue.cache_comment_count()?;
+            }
+            SortKey::CreationDate => {
+                issue.cache_cdate()?;
+            }
+            SortKey::Description => {
+                issue.cache_desc()?;
+            }
+            SortKey::DueDate => {
+                issue.cache_ddate()?;
+            }
+            SortKey::Field(name) => {
+                issue.cache_field(name)?;
+            }
+            SortKey::Milestone => {
+                issue.cache_milestone()?;
+            }
+            SortKey::ModificationDate => {
+                issue.cache_mdate()?;
+            }
+            SortKey::Tag(_) => {
+                issue.cache_tags()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn compare(&self, a: &Issue<'_>, b: &Issue<'_>) -> Ordering {
+        let ordering = match &self.key {
+            SortKey::CommentCount => a.comment_count().cmp(&b.comment_count()),
+            SortKey::CreationDate => a.cdate().cmp(b.cdate()),
+            SortKey::Description => a.desc().cmp(b.desc()),
+            SortKey::DueDate => return self.missing_last(a.ddate().as_ref(), b.ddate().as_ref()),
+            SortKey::Field(name) => return self.missing_last(a.field(name), b.field(name)),
+            SortKey::Milestone => {
+                return self.missing_last(a.milestone().as_ref(), b.milestone().as_ref())
+            }
+            SortKey::ModificationDate => a.mdate().cmp(b.mdate()),
+            // Issues having the tag come first
+            SortKey::Tag(tag) => b.tags().contains(tag).cmp(&a.tags().contains(tag)),
+        };
+        if self.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+
+    /// Compare optional values, issues without a value are sorted last in both directions
+    fn missing_last<T: Ord>(&self, a: Option<T>, b: Option<T>) -> Ordering {
+        match (a, b) {
+            (Some(x), Some(y)) if self.descending => y.cmp(&x),
+            (Some(x), Some(y)) => x.cmp(&y),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -108,6 +373,9 @@ struct Filter<'args> {
     with_tags: Vec<&'args String>,
     without_tags: Vec<&'args String>,
     milestone: MilestoneFilter<'args>,
+    overdue: bool,
+    due_within: Option<Duration>,
+    now: OffsetDateTime,
 }
 
 impl<'args> From<&'args mut FilterArgs> for Filter<'args> {
@@ -128,34 +396,51 @@ impl<'args> From<&'args mut FilterArgs> for Filter<'args> {
             milestone,
             with_tags,
             without_tags: args.without_tags.iter().collect(),
+            overdue: args.overdue,
+            due_within: args.due_within,
+            now: OffsetDateTime::now_utc(),
         }
     }
 }
 
 impl<'args> Filter<'args> {
-    fn cache<'src>(&'args self, input: Vec<Issue<'src>>) -> (Vec<Issue<'src>>, Vec<CacheError>) {
+    fn cache_issue(&self, issue: &mut Issue<'_>) -> Result<(), CacheError> {
+        if self.milestone != MilestoneFilter::Any {
+            issue.cache_milestone()?;
+        }
+        if !self.without_tags.is_empty() || !self.with_tags.is_empty() {
+            issue.cache_tags()?;
+        }
+        if self.overdue || self.due_within.is_some() {
+            issue.cache_ddate()?;
+        }
+        Ok(())
+    }
+
+    fn cache<'src>(
+        &'args self,
+        input: Vec<Issue<'src>>,
+    ) -> (Vec<Issue<'src>>, Vec<IssueLoadError>) {
         let mut errors = vec![];
         let mut result = vec![];
-        for mut issue in input {
-            if self.milestone != MilestoneFilter::Any {
-                if let Err(e) = issue.cache_milestone() {
-                    errors.push(e);
-                    continue;
-                }
-            }
-            if !self.without_tags.is_empty() || !self.with_tags.is_empty() {
-                if let Err(e) = issue.cache_tags() {
-                    errors.push(e);
-                    continue;
-                }
+        let cached = par_map(input, |mut issue| match self.cache_issue(&mut issue) {
+            Ok(_) => Ok(issue),
+            Err(e) => Err(IssueLoadError::new(issue.path(), e)),
+        });
+        for r in cached {
+            match r {
+                Ok(issue) => result.push(issue),
+                Err(e) => errors.push(e),
             }
-            result.push(issue);
         }
         (result, errors)
     }
 
-    fn apply<'src>(&'args self, input: Vec<Issue<'src>>) -> (Vec<Issue<'src>>, Vec<CacheError>) {
-        let (cached, errors): (Vec<Issue<'src>>, Vec<CacheError>) = self.cache(input);
+    fn apply<'src>(
+        &'args self,
+        input: Vec<Issue<'src>>,
+    ) -> (Vec<Issue<'src>>, Vec<IssueLoadError>) {
+        let (cached, errors) = self.cache(input);
         let issues: Vec<_> = cached
             .into_iter()
             .filter(|issue| {
@@ -172,6 +457,20 @@ impl<'args> Filter<'args> {
 
                 log::info!("Matching milestone");
 
+                if self.overdue || self.due_within.is_some() {
+                    let due = match issue.ddate() {
+                        None => return false,
+                        Some(d) => *d,
+                    };
+                    let overdue = due < self.now;
+                    let upcoming = self
+                        .due_within
+                        .is_some_and(|within| !overdue && due <= self.now.saturating_add(within));
+                    if !(self.overdue && overdue || upcoming) {
+                        return false;
+                    }
+                }
+
                 if !self.without_tags.is_empty() {
                     for tag in &self.without_tags {
                         if issue.tags().contains(tag) {
@@ -197,84 +496,179 @@ impl<'args> Filter<'args> {
 
 struct Query<'args> {
     selection: Filter<'args>,
-    projection: &'args FormatString,
-    order: Option<SortKey>,
+    projection: FormatString,
+    order: Vec<SortSpec>,
     reverse: bool,
+    group_by: Option<GroupKey>,
+    count: bool,
+    limit: Option<usize>,
+    skip: usize,
+    use_cache: bool,
+    strict: bool,
 }
 
 impl<'args> From<&'args mut Args> for Query<'args> {
     fn from(args: &'args mut Args) -> Self {
-        let projection = &args.format_string;
+        let projection = args
+            .format_string
+            .clone()
+            .with_color(args.color.enabled())
+            .with_date_format(args.date.clone());
         let selection = Filter::from(&mut args.filter);
         Self {
             selection,
             projection,
             reverse: args.reverse,
-            order: args.order,
+            order: args.order.clone(),
+            group_by: args.group_by.clone(),
+            count: args.count,
+            limit: args.limit,
+            skip: args.skip,
+            use_cache: !args.no_cache,
+            strict: args.strict,
         }
     }
 }
 
-#[allow(clippy::todo, clippy::panic_in_result_fn)]
-pub(crate) fn execute<'src>(args: &mut Args, data: &'src DataSource) {
-    let select = Query::from(args);
-    let filtered_issues = {
-        let (f, errors): (Vec<_>, Vec<_>) = {
-            let (issue_results, _issue_errors): (Vec<_>, Vec<_>) =
-                data.all().into_iter().partition(Result::is_ok);
-            let issues: Vec<Issue<'src>> = issue_results.into_iter().map(Result::unwrap).collect();
-            select.selection.apply(issues)
-        };
-        for e in errors {
+/// Stable sort by the given keys, the issue id is used as the final tiebreaker
+///
+/// Issues whose sort keys can not be read are skipped.
+fn sort<'src>(
+    mut issues: Vec<Issue<'src>>,
+    order: &[SortSpec],
+) -> (Vec<Issue<'src>>, Vec<IssueLoadError>) {
+    if order.iter().any(|spec| spec.key == SortKey::CreationDate) {
+        if let Err(e) = Issue::cache_creation(&mut issues) {
             log::error!("{}", e);
         }
-        f
-    };
-
-    let mut sorted_issues = if let Some(sort) = select.order {
-        let (cached, errors): (Vec<_>, Vec<_>) = filtered_issues
-            .into_iter()
-            .map(|mut i| -> Result<Issue<'_>, CacheError> {
-                match sort {
-                    SortKey::CreationDate => {
-                        i.cache_cdate()?;
-                    }
-                    SortKey::Description => {
-                        i.cache_desc()?;
-                    }
-                    SortKey::Milestone => {
-                        i.cache_milestone()?;
-                    }
-                    SortKey::DueDate => {
-                        i.cache_ddate()?;
-                    }
-                }
-                Ok(i)
+    }
+    let (cached, errors): (Vec<_>, Vec<_>) = par_map(issues, |mut i| {
+        match order.iter().try_for_each(|spec| spec.cache(&mut i)) {
+            Ok(_) => Ok(i),
+            Err(e) => Err(IssueLoadError::new(i.path(), e)),
+        }
+    })
+    .into_iter()
+    .partition(Result::is_ok);
+
+    let mut result: Vec<Issue<'src>> = cached.into_iter().map(Result::unwrap).collect();
+    result.sort_by(|a, b| {
+        order
+            .iter()
+            .fold(Ordering::Equal, |o, spec| {
+                o.then_with(|| spec.compare(a, b))
             })
-            .partition(Result::is_ok);
+            .then_with(|| a.id().id().cmp(b.id().id()))
+    });
+    (result, errors.into_iter().map(Result::unwrap_err).collect())
+}
 
-        for e in errors.into_iter().map(Result::unwrap_err) {
-            log::error!("{}", e);
+/// Log the skipped issues, with `strict` fail if any issue was skipped
+fn report(skipped: &[IssueLoadError], strict: bool) -> Result<(), PosixError> {
+    if skipped.is_empty() {
+        return Ok(());
+    }
+    let details: Vec<String> = skipped.iter().map(|e| format!("  {}", e)).collect();
+    log::warn!(
+        "Skipped {} issue(s):\n{}",
+        skipped.len(),
+        details.join("\n")
+    );
+    if strict {
+        return Err(PosixError::new(
+            posix_errors::EIO,
+            format!("Skipped {} issue(s)", skipped.len()),
+        ));
+    }
+    Ok(())
+}
+
+#[allow(clippy::todo, clippy::panic_in_result_fn)]
+pub(crate) fn execute<'src>(
+    args: &mut Args,
+    data: &'src DataSource,
+    out: &mut dyn Write,
+) -> Result<(), PosixError> {
+    let select = Query::from(args);
+    let (mut issues, mut skipped) = data.all_with_diagnostics();
+    if select.use_cache {
+        if let Err(e) = Issue::cache_metadata(&mut issues) {
+            log::warn!("Metadata cache: {}", e);
         }
-        let mut issues: Vec<Issue<'src>> = cached.into_iter().map(Result::unwrap).collect();
-        issues.sort_unstable_by(|a, b| match sort {
-            SortKey::CreationDate => a.cdate().cmp(b.cdate()),
-            SortKey::Description => a.desc().cmp(b.desc()),
-            SortKey::Milestone => a.milestone().cmp(b.milestone()),
-            SortKey::DueDate => a.ddate().cmp(b.ddate()),
-        });
-        issues
-    } else {
+    }
+    let (filtered_issues, errors) = select.selection.apply(issues);
+    skipped.extend(errors);
+
+    let mut sorted_issues = if select.order.is_empty() {
         filtered_issues
+    } else {
+        let (sorted, errors) = sort(filtered_issues, &select.order);
+        skipped.extend(errors);
+        sorted
     };
 
     if select.reverse {
         sorted_issues.reverse();
     }
 
-    for mut i in sorted_issues {
-        log::warn!("{}", select.projection.format(&mut i));
+    let mut page: Vec<Issue<'src>> = sorted_issues
+        .into_iter()
+        .skip(select.skip)
+        .take(select.limit.unwrap_or(usize::MAX))
+        .collect();
+    if select.projection.needs_creation() {
+        if let Err(e) = Issue::cache_creation(&mut page) {
+            log::error!("{}", e);
+        }
+    }
+
+    let keys = match (&select.group_by, select.count) {
+        (Some(key), _) => vec![key.clone()],
+        (None, true) => vec![GroupKey::Milestone, GroupKey::Tag],
+        (None, false) => vec![],
+    };
+    if keys.is_empty() {
+        for line in par_map(page, |mut i| select.projection.format(&mut i)) {
+            writeln!(out, "{}", line)?;
+        }
+        return report(&skipped, select.strict);
+    }
+
+    let mut issues = vec![];
+    for mut i in page {
+        match keys.iter().try_for_each(|key| key.cache(&mut i)) {
+            Ok(_) => issues.push(i),
+            Err(e) => skipped.push(IssueLoadError::new(i.path(), e)),
+        }
+    }
+
+    for (n, key) in keys.iter().enumerate() {
+        if n > 0 {
+            writeln!(out)?;
+        }
+        let groups = group(&issues, key);
+        if select.count {
+            if keys.len() > 1 {
+                writeln!(out, "{}:", key.name())?;
+            }
+            for (name, members) in groups {
+                writeln!(out, "{:>7} {}", members.len(), name)?;
+            }
+            continue;
+        }
+        for (g, (name, members)) in groups.into_iter().enumerate() {
+            if g > 0 {
+                writeln!(out)?;
+            }
+            writeln!(out, "{} ({})", name, members.len())?;
+            for index in members {
+                if let Some(i) = issues.get_mut(index) {
+                    writeln!(out, "{}", select.projection.format(i))?;
+                }
+            }
+        }
     }
+    report(&skipped, select.strict)
 }
 
 #[cfg(not(tarpaulin_include))]
@@ -283,7 +677,7 @@ fn main() {
     let mut args = Args::parse();
     cli_log::init_with_level(args.verbose.log_level_filter());
     log::debug!("Log Level is set to {}", log::max_level());
-    let data = match git_issue::DataSource::try_new(&args.git) {
+    let data = match git_issue::DataSource::try_new_at(&args.git, args.at.as_deref()) {
         Err(e) => {
             let err: PosixError = e.into();
             log::error!(" error: {}", err);
@@ -292,5 +686,298 @@ fn main() {
         Ok(repo) => repo,
     };
 
-    execute(&mut args, &data);
+    let mut out = if args.no_pager {
+        Output::stdout()
+    } else {
+        Output::pager(&data.repo)
+    };
+    let result = execute(&mut args, &data, &mut out);
+    drop(out);
+    match result {
+        // The reader, e.g. the pager, went away
+        Err(e) if e.code() == posix_errors::EPIPE => {}
+        Err(e) => {
+            log::error!("{}", e);
+            std::process::exit(e.code());
+        }
+        Ok(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod parse_args {
+    use crate::{Args, GroupKey, SortKey, SortSpec};
+    use clap::Parser;
+
+    #[test]
+    fn no_arguments() {
+        let _args: Args = Parser::try_parse_from(["git-issue-list"]).expect("No arguments");
+    }
+
+    #[test]
+    fn milestone_conflicts() {
+        let result: Result<Args, _> = Parser::try_parse_from(["git-issue-list", "-m", "v1", "-M"]);
+        assert!(result.is_err(), "-m & -M are mutually exclusive");
+    }
+
+    #[test]
+    fn order() {
+        let args: Args = Parser::try_parse_from(["git-issue-list", "-o", "%M,-%d,tag:bug"])
+            .expect("Multiple sort keys");
+        let expected = vec![
+            SortSpec {
+                key: SortKey::Milestone,
+                descending: false,
+            },
+            SortSpec {
+                key: SortKey::DueDate,
+                descending: true,
+            },
+            SortSpec {
+                key: SortKey::Tag("bug".to_owned()),
+                descending: false,
+            },
+        ];
+        assert_eq!(args.order, expected);
+
+        let result: Result<Args, _> = Parser::try_parse_from(["git-issue-list", "-o", "%x"]);
+        assert!(result.is_err(), "Unknown sort key");
+        let result2: Result<Args, _> =
+            Parser::try_parse_from(["git-issue-list", "-o", "field:../tags"]);
+        assert!(result2.is_err(), "Invalid field name");
+    }
+
+    #[test]
+    fn group_by() {
+        let args: Args = Parser::try_parse_from(["git-issue-list", "--group-by", "assignee"])
+            .expect("Group by assignee");
+        assert_eq!(args.group_by, Some(GroupKey::Field("assignee".to_owned())));
+        let args2: Args = Parser::try_parse_from(["git-issue-list", "--facets"]).expect("Facets");
+        assert!(args2.count);
+        let result: Result<Args, _> =
+            Parser::try_parse_from(["git-issue-list", "--group-by", "foo"]);
+        assert!(result.is_err(), "Unknown group key");
+    }
+
+    #[test]
+    fn date() {
+        let _args: Args = Parser::try_parse_from(["git-issue-list", "--date", "relative"])
+            .expect("Relative dates");
+        let _args2: Args =
+            Parser::try_parse_from(["git-issue-list", "--date=format:%Y"]).expect("strftime dates");
+        let result: Result<Args, _> = Parser::try_parse_from(["git-issue-list", "--date", "foo"]);
+        assert!(result.is_err(), "Unknown date format");
+    }
+}
+
+#[cfg(test)]
+mod sort {
+    use git_issue::{DataSource, Id};
+
+    fn ids(data: &DataSource, order: &str) -> Vec<Id> {
+        let specs: Vec<crate::SortSpec> = order.split(',').map(|s| s.parse().unwrap()).collect();
+        let issues = data.all().map(Result::unwrap).collect();
+        crate::sort(issues, &specs)
+            .0
+            .iter()
+            .map(|i| i.id().clone())
+            .collect()
+    }
+
+    #[test]
+    fn multiple_keys() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let tmp = tmp_dir.path();
+        git_wrapper::setup_test_author();
+        git_issue::create(tmp, false).unwrap();
+        let data = DataSource::try_from(tmp).unwrap();
+        let a = data
+            .create_issue("A", vec!["bug".to_owned()], Some("v2".to_owned()))
+            .unwrap();
+        let b = data
+            .create_issue("B", vec![], Some("v1".to_owned()))
+            .unwrap();
+        let c = data.create_issue("C", vec![], None).unwrap();
+        let d = data
+            .create_issue("D", vec![], Some("v1".to_owned()))
+            .unwrap();
+
+        assert_eq!(
+            ids(&data, "%M,%D"),
+            vec![b.clone(), d.clone(), a.clone(), c.clone()]
+        );
+        assert_eq!(
+            ids(&data, "-%M,-%D"),
+            vec![a.clone(), d.clone(), b.clone(), c.clone()]
+        );
+        assert_eq!(ids(&data, "tag:bug,%D"), vec![a, b, c, d]);
+    }
+
+    #[test]
+    fn id_tiebreaker() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let tmp = tmp_dir.path();
+        git_wrapper::setup_test_author();
+        git_issue::create(tmp, false).unwrap();
+        let data = DataSource::try_from(tmp).unwrap();
+        let mut expected = vec![
+            data.create_issue("Same", vec![], None).unwrap(),
+            data.create_issue("Same", vec![], None).unwrap(),
+            data.create_issue("Same", vec![], None).unwrap(),
+        ];
+        expected.sort_by(|a, b| a.id().cmp(b.id()));
+
+        assert_eq!(ids(&data, "%D,%d"), expected);
+    }
+}
+
+#[cfg(test)]
+mod group {
+    use git_issue::{DataSource, Issue};
+
+    use crate::GroupKey;
+
+    fn titles(issues: &[Issue<'_>], key: &GroupKey) -> Vec<(String, Vec<String>)> {
+        crate::group(issues, key)
+            .into_iter()
+            .map(|(name, members)| {
+                let titles = members.into_iter().map(|i| issues[i].title()).collect();
+                (name, titles)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn milestone_and_tag() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let tmp = tmp_dir.path();
+        git_wrapper::setup_test_author();
+        git_issue::create(tmp, false).unwrap();
+        let data = DataSource::try_from(tmp).unwrap();
+        data.create_issue("A", vec!["bug".to_owned()], Some("v1".to_owned()))
+            .unwrap();
+        data.create_issue("B", vec![], None).unwrap();
+        let mut issues: Vec<Issue<'_>> = data.all().map(Result::unwrap).collect();
+        for issue in &mut issues {
+            issue.cache_desc().unwrap();
+            GroupKey::Milestone.cache(issue).unwrap();
+            GroupKey::Tag.cache(issue).unwrap();
+        }
+        issues.sort_by_key(Issue::title);
+
+        let expected = vec![
+            ("v1".to_owned(), vec!["A".to_owned()]),
+            ("(no milestone)".to_owned(), vec!["B".to_owned()]),
+        ];
+        assert_eq!(titles(&issues, &GroupKey::Milestone), expected);
+
+        let expected2 = vec![
+            ("bug".to_owned(), vec!["A".to_owned()]),
+            ("open".to_owned(), vec!["A".to_owned(), "B".to_owned()]),
+        ];
+        assert_eq!(titles(&issues, &GroupKey::Tag), expected2);
+    }
+}
+
+#[cfg(test)]
+mod cmd_list {
+    use clap::Parser;
+    use git_issue::DataSource;
+
+    use crate::Args;
+
+    fn run(data: &DataSource, args: &[&str]) -> String {
+        let mut argv = vec!["git-issue-list", "-l", "%D"];
+        argv.extend_from_slice(args);
+        let mut args: Args = Parser::try_parse_from(argv).unwrap();
+        let mut out = vec![];
+        crate::execute(&mut args, data, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn due_date() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let tmp = tmp_dir.path();
+        git_wrapper::setup_test_author();
+        git_issue::create(tmp, false).unwrap();
+        let data = DataSource::try_from(tmp).unwrap();
+        let now = time::OffsetDateTime::now_utc();
+        for (title, offset) in [("Past", -2), ("Soon", 3), ("Later", 30)] {
+            let id = data.create_issue(title, vec![], None).unwrap();
+            let due = now + time::Duration::days(offset);
+            std::fs::write(
+                id.path(&data.issues_dir).join("duedate"),
+                due.format(&time::format_description::well_known::Rfc3339)
+                    .unwrap(),
+            )
+            .unwrap();
+        }
+        data.create_issue("Never", vec![], None).unwrap();
+
+        assert_eq!(run(&data, &["--overdue"]), "Past\n");
+        assert_eq!(run(&data, &["--due-within", "7d"]), "Soon\n");
+        assert_eq!(
+            run(&data, &["--overdue", "--due-within", "1w", "-o", "%d"]),
+            "Past\nSoon\n"
+        );
+    }
+
+    #[test]
+    fn limit_and_skip() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let tmp = tmp_dir.path();
+        git_wrapper::setup_test_author();
+        git_issue::create(tmp, false).unwrap();
+        let data = DataSource::try_from(tmp).unwrap();
+        for title in ["A", "B", "C", "D"] {
+            data.create_issue(title, vec![], None).unwrap();
+        }
+
+        assert_eq!(run(&data, &["-o", "%D"]), "A\nB\nC\nD\n");
+        assert_eq!(run(&data, &["-o", "%D", "-n", "2"]), "A\nB\n");
+        assert_eq!(
+            run(&data, &["-o", "%D", "--skip", "1", "-n", "2"]),
+            "B\nC\n"
+        );
+        assert_eq!(run(&data, &["-o", "%D", "--skip", "5"]), "");
+    }
+
+    #[test]
+    fn no_cache() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let tmp = tmp_dir.path();
+        git_wrapper::setup_test_author();
+        git_issue::create(tmp, false).unwrap();
+        let data = DataSource::try_from(tmp).unwrap();
+        data.create_issue("Title", vec![], None).unwrap();
+
+        assert_eq!(run(&data, &[]), "Title\n");
+        let path = data.issues_dir.join(".git/git-issue/cache");
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, text.replace("\tTitle\t", "\tCached\t")).unwrap();
+        assert_eq!(run(&data, &[]), "Cached\n");
+        assert_eq!(run(&data, &["--no-cache"]), "Title\n");
+    }
+
+    #[test]
+    fn strict() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let tmp = tmp_dir.path();
+        git_wrapper::setup_test_author();
+        git_issue::create(tmp, false).unwrap();
+        let data = DataSource::try_from(tmp).unwrap();
+        data.create_issue("Valid", vec![], None).unwrap();
+        let broken = data.create_issue("Broken", vec![], None).unwrap();
+        std::fs::write(broken.path(&data.issues_dir).join("duedate"), "soon").unwrap();
+
+        assert_eq!(run(&data, &["--due-within", "1w"]), "");
+        assert_eq!(run(&data, &["-o", "%D"]), "Broken\nValid\n");
+
+        let argv = ["git-issue-list", "--strict", "--due-within", "1w"];
+        let mut args: Args = Parser::try_parse_from(argv).unwrap();
+        let mut out = vec![];
+        let err = crate::execute(&mut args, &data, &mut out).unwrap_err();
+        assert_eq!(err.code(), posix_errors::EIO);
+    }
 }
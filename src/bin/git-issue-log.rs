@@ -0,0 +1,186 @@
+#![allow(missing_docs)]
+use std::io::Write;
+
+use clap::Parser;
+use clap_git_options::GitOptions;
+use clap_verbosity_flag::{Verbosity, WarnLevel};
+use time::OffsetDateTime;
+
+use posix_errors::PosixError;
+
+use git_issue::{DataSource, DateFormat, Event, Output};
+
+#[derive(Parser)]
+#[clap(
+    author,
+    version,
+    about = "Show the activity log of all issues",
+    help_expected = true,
+    dont_collapse_args_in_usage = true
+)]
+struct Args {
+    /// Only show changes after this date, e.g. "2 weeks ago" or 2022-10-01
+    #[clap(long, value_name = "DATE")]
+    since: Option<String>,
+
+    /// Only show changes by authors whose name or email contains the text
+    #[clap(long, value_name = "TEXT")]
+    author: Option<String>,
+
+    /// Only show changes to this issue
+    #[clap(long, value_name = "ID")]
+    issue: Option<String>,
+
+    /// Date format: default, iso, iso-strict, rfc, short, relative, unix or format:<strftime>.
+    /// Append -local to use the local timezone
+    #[clap(
+        long,
+        value_name = "FORMAT",
+        default_value = "iso",
+        help_heading = "OUTPUT"
+    )]
+    date: DateFormat,
+
+    /// Do not pipe the output into a pager
+    #[clap(long, help_heading = "OUTPUT")]
+    no_pager: bool,
+
+    #[clap(flatten)]
+    git: GitOptions,
+
+    #[clap(flatten, next_help_heading = "OUTPUT")]
+    verbose: Verbosity<WarnLevel>,
+}
+
+struct Filter {
+    since: Option<OffsetDateTime>,
+    author: Option<String>,
+}
+
+impl Filter {
+    fn matches(&self, event: &Event) -> bool {
+        self.since.is_none_or(|since| *event.date() >= since)
+            && self.author.as_ref().is_none_or(|author| {
+                event.author_name().contains(author.as_str())
+                    || event.author_email().contains(author.as_str())
+            })
+    }
+}
+
+fn execute(args: &Args, data: &DataSource, out: &mut dyn Write) -> Result<(), PosixError> {
+    let since = match &args.since {
+        Some(text) => Some(git_issue::approxidate(&data.repo, text).ok_or_else(|| {
+            PosixError::new(
+                posix_errors::EINVAL,
+                format!("Failed to parse date “{}”", text),
+            )
+        })?),
+        None => None,
+    };
+    let id = args
+        .issue
+        .as_ref()
+        .map(|needle| data.find_issue(needle))
+        .transpose()?;
+    let filter = Filter {
+        since,
+        author: args.author.clone(),
+    };
+
+    for event in data
+        .events(id.as_ref())?
+        .iter()
+        .filter(|e| filter.matches(e))
+    {
+        writeln!(
+            out,
+            "{}  {}  {}: {} “{}”",
+            args.date.format(event.date()),
+            event.id().short_id(),
+            event.author_name(),
+            event.kind(),
+            event.title()
+        )?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(not(tarpaulin_include))]
+#[allow(clippy::exit)]
+fn main() {
+    let args = Args::parse();
+    cli_log::init_with_level(args.verbose.log_level_filter());
+    log::debug!("Log Level is set to {}", log::max_level());
+    let data = match git_issue::DataSource::try_new(&args.git) {
+        Err(e) => {
+            let err: PosixError = e.into();
+            log::error!("{}", err);
+            std::process::exit(err.code());
+        }
+        Ok(repo) => repo,
+    };
+
+    let mut out = if args.no_pager {
+        Output::stdout()
+    } else {
+        Output::pager(&data.repo)
+    };
+    let result = execute(&args, &data, &mut out);
+    drop(out);
+    match result {
+        // The reader, e.g. the pager, went away
+        Err(e) if e.code() == posix_errors::EPIPE => {}
+        Err(e) => {
+            log::error!("{}", e);
+            std::process::exit(e.code());
+        }
+        Ok(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod cmd_log {
+    use clap::Parser;
+    use git_issue::DataSource;
+
+    fn log(data: &DataSource, args: &[&str]) -> Vec<String> {
+        let args = Parser::try_parse_from(args).expect("Parsed arguments");
+        let mut out: Vec<u8> = vec![];
+        crate::execute(&args, data, &mut out).expect("Printed log");
+        String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|l| l.split_once("  ").unwrap().1.to_owned())
+            .collect()
+    }
+
+    #[test]
+    fn filters() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let tmp = tmp_dir.path();
+        git_wrapper::setup_test_author();
+        git_issue::create(tmp, false).unwrap();
+        let data = DataSource::try_from(tmp).unwrap();
+        let first = data
+            .create_issue("First", vec!["bug".to_owned()], None)
+            .unwrap();
+        let second = data.create_issue("Second", vec![], None).unwrap();
+        data.close_issue(&first).unwrap();
+
+        let all = log(&data, &["git-issue-log"]);
+        assert_eq!(all.len(), 4);
+        assert!(all[0].ends_with("created “First”"));
+        assert_eq!(
+            all[3],
+            format!("{}  {}: closed “First”", first.short_id(), "Max Musterman")
+        );
+
+        let only = log(&data, &["git-issue-log", "--issue", second.short_id()]);
+        assert_eq!(only.len(), 1);
+        assert!(only[0].starts_with(second.short_id()));
+
+        assert!(log(&data, &["git-issue-log", "--author", "nobody"]).is_empty());
+        assert!(log(&data, &["git-issue-log", "--since", "2099-01-01"]).is_empty());
+    }
+}
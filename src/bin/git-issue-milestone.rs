@@ -1,6 +1,8 @@
 #![allow(missing_docs)]
 
 use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
 
 use clap::{Parser, Subcommand};
 use clap_git_options::GitOptions;
@@ -8,10 +10,29 @@ use clap_verbosity_flag::{Verbosity, WarnLevel};
 
 use posix_errors::PosixError;
 
-use git_issue::{DataSource, Issue, WriteResult};
+use time::{Date, OffsetDateTime, UtcOffset};
+
+use git_issue::{DataSource, HistoryCommit, Id, Issue, State, WriteResult};
 
 #[derive(Subcommand)]
 enum Command {
+    /// Show the daily number of open issues in a milestone
+    Burndown {
+        /// Milestone name
+        milestone: String,
+
+        /// Also write the chart as SVG to this file
+        #[clap(long, value_name = "FILE")]
+        svg: Option<PathBuf>,
+
+        /// Maximum chart width in columns
+        #[clap(long, value_name = "N", default_value = "60")]
+        width: usize,
+
+        /// Maximum chart height in lines
+        #[clap(long, value_name = "N", default_value = "10")]
+        height: usize,
+    },
     /// List milestones
     List {
         /// List milestones without open issues
@@ -58,16 +79,16 @@ struct Args {
     verbose: Verbosity<WarnLevel>,
 }
 
-fn set_cmd(mut data: DataSource, issue_id: &str, milestone: &str) -> Result<(), PosixError> {
+fn set_cmd(data: DataSource, issue_id: &str, milestone: &str) -> Result<(), PosixError> {
     let id = data.find_issue(issue_id).map_err(PosixError::from)?;
     log::info!("Starting transaction");
-    data.start_transaction().map_err(PosixError::from)?;
+    let transaction = data.transaction().map_err(PosixError::from)?;
 
     match data.add_milestone(&id, milestone) {
         Err(e) => {
             log::error!("{}", e);
             log::info!("Rolling back transaction");
-            data.rollback_transaction().map_err(PosixError::from)?;
+            transaction.rollback().map_err(PosixError::from)?;
             Err(PosixError::from(e))
         }
         Ok(WriteResult::NoChanges) => {
@@ -77,37 +98,36 @@ fn set_cmd(mut data: DataSource, issue_id: &str, milestone: &str) -> Result<(),
                 &id.short_id()
             );
             log::info!("Rolling back transaction");
-            data.rollback_transaction().map_err(PosixError::from)
+            transaction.rollback().map_err(PosixError::from)
         }
         Ok(WriteResult::Applied) => {
             log::warn!("Set milestone “{}” on issue {}", milestone, &id.short_id());
             log::info!("Committing transaction");
-            data.finish_transaction_without_merge()
-                .map_err(PosixError::from)
+            transaction.commit_without_merge().map_err(PosixError::from)
         }
     }
 }
 
-fn remove_cmd(mut data: DataSource, issue_id: &str) -> Result<(), PosixError> {
+fn remove_cmd(data: DataSource, issue_id: &str) -> Result<(), PosixError> {
     let id = data.find_issue(issue_id).map_err(PosixError::from)?;
     log::info!("Starting transaction");
+    let transaction = data.transaction().map_err(PosixError::from)?;
     match data.remove_milestone(&id) {
         Err(e) => {
             log::error!("{}", e);
             log::info!("Rolling back transaction");
-            data.rollback_transaction().map_err(PosixError::from)?;
+            transaction.rollback().map_err(PosixError::from)?;
             Err(PosixError::from(e))
         }
         Ok(WriteResult::NoChanges) => {
             log::warn!("Issue already has no milestone {}", &id.short_id());
             log::info!("Rolling back transaction");
-            data.rollback_transaction().map_err(PosixError::from)
+            transaction.rollback().map_err(PosixError::from)
         }
         Ok(WriteResult::Applied) => {
             log::warn!("Removed milestone from issue {}", &id.short_id());
             log::info!("Committing transaction");
-            data.finish_transaction_without_merge()
-                .map_err(PosixError::from)
+            transaction.commit_without_merge().map_err(PosixError::from)
         }
     }
 }
@@ -219,6 +239,310 @@ fn list_cmd(data: &DataSource, all: bool) -> Result<(), PosixError> {
     }
 }
 
+/// Daily number of open issues in a milestone
+struct Burndown {
+    /// Day the first issue was added to the milestone
+    start: Date,
+    /// Open issues at the end of each day since `start`
+    counts: Vec<usize>,
+    /// Milestone due date
+    due: Option<Date>,
+}
+
+impl Burndown {
+    /// Replay the history of the `milestone` & `tags` files up to `today`
+    fn new(commits: &[HistoryCommit], milestone: &str, today: Date) -> Option<Self> {
+        let mut issues: HashMap<&Id, (bool, bool)> = HashMap::new();
+        let mut days: Vec<(Date, usize)> = vec![];
+        for commit in commits {
+            for change in commit.changes() {
+                let (in_milestone, open) = issues.entry(change.id()).or_insert((false, false));
+                match change.file().as_str() {
+                    "milestone" => {
+                        *in_milestone = change.new().as_deref() == Some(milestone);
+                    }
+                    "tags" => {
+                        *open = change
+                            .new()
+                            .as_deref()
+                            .is_some_and(|t| State::from_tags(t) == State::Open);
+                    }
+                    _ => {}
+                }
+            }
+            let count = issues.values().filter(|(m, o)| *m && *o).count();
+            let day = commit.date().to_offset(UtcOffset::UTC).date();
+            match days.last_mut() {
+                Some((last, value)) if *last == day => *value = count,
+                Some(_) => days.push((day, count)),
+                None if issues.values().any(|(m, _)| *m) => days.push((day, count)),
+                None => {}
+            }
+        }
+
+        let start = days.first()?.0;
+        let mut counts = vec![];
+        let mut current = 0;
+        let mut day = start;
+        let mut changes = days.into_iter().peekable();
+        while day <= today {
+            if let Some((_, count)) = changes.next_if(|(d, _)| *d <= day) {
+                current = count;
+                // Skip days from the future, i.e. commits with a bogus date
+                while let Some((_, count)) = changes.next_if(|(d, _)| *d <= day) {
+                    current = count;
+                }
+            }
+            counts.push(current);
+            day = match day.next_day() {
+                Some(d) => d,
+                None => break,
+            };
+        }
+        Some(Self {
+            start,
+            counts,
+            due: None,
+        })
+    }
+
+    /// Number of days shown in the chart
+    // arithmetic: the result is at most the number of days between two valid dates
+    #[allow(clippy::arithmetic_side_effects, clippy::cast_sign_loss)]
+    fn days(&self) -> usize {
+        let due_days = self
+            .due
+            .map_or(0, |due| (due - self.start).whole_days().max(0) as usize + 1);
+        self.counts.len().max(due_days).max(1)
+    }
+
+    fn max(&self) -> usize {
+        self.counts.iter().copied().max().unwrap_or(0).max(1)
+    }
+
+    /// Ideal number of open issues on day `n`, falling linearly to zero at the due date
+    #[allow(clippy::cast_precision_loss, clippy::float_arithmetic)]
+    fn ideal(&self, n: usize) -> Option<f64> {
+        let due = self.due?;
+        let total = (due - self.start).whole_days();
+        if total <= 0 {
+            return None;
+        }
+        let first = *self.counts.first()? as f64;
+        let remaining = 1.0 - n as f64 / total as f64;
+        (remaining >= 0.0).then_some(first * remaining)
+    }
+
+    /// Render an ASCII chart, `*` marks the actual & `.` the ideal number of open issues
+    #[allow(
+        clippy::arithmetic_side_effects,
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::float_arithmetic,
+        clippy::integer_division
+    )]
+    fn ascii(&self, width: usize, height: usize) -> String {
+        let days = self.days();
+        let columns = days.min(width.max(1));
+        let rows = self.max().min(height.max(1));
+        let scale = self.max() as f64 / rows as f64;
+        // Last day shown in each column
+        let day = |column: usize| (column + 1) * days / columns - 1;
+        let row = |value: f64| (value / scale).round() as usize;
+
+        let label_width = self.max().to_string().len();
+        let mut result = String::new();
+        for r in (0..=rows).rev() {
+            let label = if r == rows || r == 0 {
+                format!("{:>w$}", (r as f64 * scale).round(), w = label_width)
+            } else {
+                " ".repeat(label_width)
+            };
+            result.push_str(&label);
+            result.push_str(" |");
+            for c in 0..columns {
+                let n = day(c);
+                let actual = self.counts.get(n).map(|v| row(*v as f64));
+                let ideal = self.ideal(n).map(row);
+                result.push(if actual == Some(r) {
+                    '*'
+                } else if ideal == Some(r) {
+                    '.'
+                } else {
+                    ' '
+                });
+            }
+            result.truncate(result.trim_end_matches(' ').len());
+            result.push('\n');
+        }
+        let end = self.start + time::Duration::days(days as i64 - 1);
+        let start_label = self.start.to_string();
+        let end_label = end.to_string();
+        // Writing to a String never fails
+        #[allow(unused_must_use)]
+        {
+            writeln!(
+                result,
+                "{} +{}",
+                " ".repeat(label_width),
+                "-".repeat(columns)
+            );
+            let gap = (columns + 1).saturating_sub(start_label.len());
+            if days > 1 && gap > end_label.len() {
+                writeln!(
+                    result,
+                    "{}  {}{:>gap$}",
+                    " ".repeat(label_width),
+                    start_label,
+                    end_label,
+                    gap = gap
+                );
+            } else {
+                writeln!(result, "{}  {}", " ".repeat(label_width), start_label);
+            }
+        }
+        result
+    }
+
+    /// Render an SVG chart with the actual & the dashed ideal line
+    #[allow(
+        clippy::arithmetic_side_effects,
+        clippy::cast_precision_loss,
+        clippy::float_arithmetic
+    )]
+    fn svg(&self, milestone: &str) -> String {
+        let (width, height, margin) = (640.0, 320.0, 40.0);
+        let span = (self.days().max(2) - 1) as f64;
+        let x = |n: usize| margin + n as f64 * (width - 2.0 * margin) / span;
+        let y = |v: f64| height - margin - v * (height - 2.0 * margin) / self.max() as f64;
+        let points = |values: Vec<(usize, f64)>| {
+            values
+                .into_iter()
+                .map(|(n, v)| format!("{:.1},{:.1}", x(n), y(v)))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        let actual = points(
+            self.counts
+                .iter()
+                .enumerate()
+                .map(|(n, v)| (n, *v as f64))
+                .collect(),
+        );
+        let ideal = points(
+            (0..self.days())
+                .filter_map(|n| self.ideal(n).map(|v| (n, v)))
+                .collect(),
+        );
+        let end = self.start + time::Duration::days(self.days() as i64 - 1);
+        let escaped = milestone
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;");
+
+        let mut result = String::new();
+        // Writing to a String never fails
+        #[allow(unused_must_use)]
+        {
+            writeln!(
+                result,
+                r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="sans-serif" font-size="12">"#,
+                w = width,
+                h = height
+            );
+            writeln!(result, r#"<rect width="100%" height="100%" fill="white"/>"#);
+            writeln!(
+                result,
+                r#"<text x="{}" y="20" text-anchor="middle" font-size="14">Burndown {}</text>"#,
+                width / 2.0,
+                escaped
+            );
+            writeln!(
+                result,
+                r#"<path d="M{m},{m} V{b} H{r}" fill="none" stroke="black"/>"#,
+                m = margin,
+                b = height - margin,
+                r = width - margin
+            );
+            writeln!(
+                result,
+                r#"<text x="{}" y="{}" text-anchor="end">{}</text>"#,
+                margin - 4.0,
+                margin + 4.0,
+                self.max()
+            );
+            writeln!(
+                result,
+                r#"<text x="{}" y="{}" text-anchor="end">0</text>"#,
+                margin - 4.0,
+                height - margin + 4.0
+            );
+            writeln!(
+                result,
+                r#"<text x="{}" y="{}">{}</text>"#,
+                margin,
+                height - margin + 16.0,
+                self.start
+            );
+            writeln!(
+                result,
+                r#"<text x="{}" y="{}" text-anchor="end">{}</text>"#,
+                width - margin,
+                height - margin + 16.0,
+                end
+            );
+            if !ideal.is_empty() {
+                writeln!(
+                    result,
+                    r#"<polyline points="{}" fill="none" stroke="gray" stroke-dasharray="4 4"/>"#,
+                    ideal
+                );
+            }
+            writeln!(
+                result,
+                r#"<polyline points="{}" fill="none" stroke="steelblue" stroke-width="2"/>"#,
+                actual
+            );
+            writeln!(result, "</svg>");
+        }
+        result
+    }
+}
+
+#[allow(clippy::print_stdout)]
+fn burndown_cmd(
+    data: &DataSource,
+    milestone: &str,
+    svg: Option<&Path>,
+    width: usize,
+    height: usize,
+) -> Result<(), PosixError> {
+    let commits = data.history(None, &["milestone", "tags"])?;
+    let today = OffsetDateTime::now_utc().date();
+    let mut chart = Burndown::new(&commits, milestone, today).ok_or_else(|| {
+        PosixError::new(
+            posix_errors::ENOENT,
+            format!("No issues found for milestone “{}”", milestone),
+        )
+    })?;
+    chart.due = data
+        .milestone_duedate(milestone)
+        .map(|d| d.to_offset(UtcOffset::UTC).date());
+
+    match chart.due {
+        Some(due) => println!("Milestone {} (due {})", milestone, due),
+        None => println!("Milestone {}", milestone),
+    }
+    println!();
+    print!("{}", chart.ascii(width, height));
+
+    if let Some(path) = svg {
+        std::fs::write(path, chart.svg(milestone))?;
+    }
+    Ok(())
+}
+
 #[cfg(not(tarpaulin_include))]
 #[allow(clippy::exit)]
 fn main() {
@@ -236,6 +560,12 @@ fn main() {
 
     if let Err(e) = match args.command {
         None => list_cmd(&data, false),
+        Some(Command::Burndown {
+            milestone,
+            svg,
+            width,
+            height,
+        }) => burndown_cmd(&data, &milestone, svg.as_deref(), width, height),
         Some(Command::List { all }) => list_cmd(&data, all),
         Some(Command::Remove { issue_id }) => remove_cmd(data, &issue_id),
         Some(Command::Set {
@@ -247,3 +577,67 @@ fn main() {
         std::process::exit(e.code());
     }
 }
+
+#[cfg(test)]
+mod burndown {
+    use git_issue::DataSource;
+    use time::{Date, Month, OffsetDateTime};
+
+    use crate::Burndown;
+
+    #[test]
+    fn from_history() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let tmp = tmp_dir.path();
+        git_wrapper::setup_test_author();
+        git_issue::create(tmp, false).unwrap();
+        let data = DataSource::try_from(tmp).unwrap();
+        data.create_issue("A", vec![], Some("v1".to_owned()))
+            .unwrap();
+        let b = data
+            .create_issue("B", vec![], Some("v1".to_owned()))
+            .unwrap();
+        data.create_issue("C", vec![], Some("v2".to_owned()))
+            .unwrap();
+        data.close_issue(&b).unwrap();
+
+        let commits = data.history(None, &["milestone", "tags"]).unwrap();
+        let today = OffsetDateTime::now_utc().date();
+        let chart = Burndown::new(&commits, "v1", today).expect("Milestone history");
+        assert_eq!(chart.counts, vec![1]);
+        assert!(Burndown::new(&commits, "v3", today).is_none());
+    }
+
+    #[test]
+    fn ascii() {
+        let start = Date::from_calendar_date(2022, Month::October, 1).unwrap();
+        let mut chart = Burndown {
+            start,
+            counts: vec![4, 4, 3, 1],
+            due: None,
+        };
+        let expected = "\
+4 |**
+  |  *
+  |
+  |   *
+0 |
+  +----
+   2022-10-01
+";
+        assert_eq!(chart.ascii(60, 10), expected);
+
+        chart.due = Date::from_calendar_date(2022, Month::October, 5).ok();
+        let expected2 = "\
+4 |**
+  | .*
+  |  .
+  |   *
+0 |    .
+  +-----
+   2022-10-01
+";
+        assert_eq!(chart.ascii(60, 10), expected2);
+        assert!(chart.svg("v1").contains("stroke-dasharray"));
+    }
+}
@@ -5,6 +5,8 @@ use clap_verbosity_flag::{Verbosity, WarnLevel};
 
 use posix_errors::PosixError;
 
+use git_issue::MergeStrategy;
+
 #[derive(Parser)]
 #[clap(
     author,
@@ -29,6 +31,22 @@ struct Args {
     #[clap(short, long, long_help = "Edit the issue")]
     edit: bool,
 
+    #[clap(
+        long,
+        value_enum,
+        value_name = "STRATEGY",
+        long_help = "How the issue changes are committed, overrides issues.strategy in .issues/config"
+    )]
+    strategy: Option<MergeStrategy>,
+
+    #[clap(
+        long = "ref",
+        value_name = "REF",
+        long_help = "Commit the issue changes to this reference without touching the working \
+                     tree, e.g. refs/heads/issues, overrides issues.ref in .issues/config"
+    )]
+    reference: Option<String>,
+
     #[clap(flatten, next_help_heading = "Output")]
     verbose: Verbosity<WarnLevel>,
 
@@ -38,8 +56,16 @@ struct Args {
 
 fn execute(
     args: &Args,
-    mut data: git_issue::DataSource,
+    data: git_issue::DataSource,
 ) -> Result<(git_issue::Id, String), PosixError> {
+    let data = match args.strategy {
+        Some(strategy) => data.with_strategy(strategy),
+        None => data,
+    };
+    let data = match &args.reference {
+        Some(reference) => data.with_ref(reference)?,
+        None => data,
+    };
     let empty: Vec<String> = vec![];
     let tags = args.tags.as_ref().unwrap_or(&empty).clone();
     let milestone = args.milestone.clone();
@@ -54,7 +80,7 @@ fn execute(
         args.summary.as_ref().expect("Summary is provided").clone()
     };
 
-    data.start_transaction()?;
+    let transaction = data.transaction()?;
     match data.create_issue(&description, tags, milestone) {
         Ok(id) => {
             let title = description
@@ -62,15 +88,13 @@ fn execute(
                 .next()
                 .expect("Expected at least one line");
             let message = format!("gi({}): {}", &id.short_id(), &title);
-            #[cfg(not(feature = "strict-compatibility"))]
-            log::info!("Merging issue creation as not fast forward branch");
-            data.finish_transaction(&message)?;
+            transaction.commit(&message)?;
             Ok((id, title.to_owned()))
         }
         Err(e) => {
             log::error!("{}", e);
             log::warn!("Rolling back transaction");
-            data.rollback_transaction()?;
+            transaction.rollback()?;
             Err(e.into())
         }
     }
@@ -90,7 +114,10 @@ fn main() {
     };
     match execute(&args, data) {
         Ok((id, title)) => log::warn!("Added issue {}: {}", &id.short_id(), title),
-        Err(e) => std::process::exit(e.code()),
+        Err(e) => {
+            log::error!(" error: {}", e);
+            std::process::exit(e.code());
+        }
     }
 }
 
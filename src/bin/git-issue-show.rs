@@ -1,11 +1,15 @@
 #![allow(missing_docs)]
+use std::io::Write;
+
 use clap::Parser;
 use clap_git_options::GitOptions;
 use clap_verbosity_flag::{Verbosity, WarnLevel};
 
 use posix_errors::PosixError;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
 
-use git_issue::DataSource;
+use git_issue::{DataSource, DateFormat, DiffLine, HistoryEntry, Output, Property};
 
 #[derive(Parser)]
 #[clap(
@@ -23,6 +27,19 @@ struct Args {
     #[clap(short, long)]
     comments: bool,
 
+    /// Date format: default, iso, iso-strict, rfc, short, relative, unix or format:<strftime>.
+    /// Append -local to use the local timezone
+    #[clap(long, value_name = "FORMAT", default_value = "default")]
+    date: DateFormat,
+
+    /// Do not pipe the output into a pager
+    #[clap(long)]
+    no_pager: bool,
+
+    /// Show the issues as of this revision, e.g. a tag or another branch
+    #[clap(long, value_name = "REV")]
+    at: Option<String>,
+
     #[clap(flatten)]
     git: GitOptions,
 
@@ -30,68 +47,108 @@ struct Args {
     verbose: Verbosity<WarnLevel>,
 }
 
-#[allow(clippy::print_stdout)]
-fn execute(args: &Args, data: &DataSource) -> Result<(), PosixError> {
+/// Describe a property change, `None` if nothing visible changed
+fn summary(entry: &HistoryEntry, date_format: &DateFormat) -> Option<String> {
+    let (old, new) = (entry.old().as_ref(), entry.new().as_ref());
+    let text = match (entry.property(), new) {
+        (Property::Description, Some(_)) if old.is_none() => "added description".to_owned(),
+        (Property::Description, Some(_)) => {
+            let diff = entry.diff();
+            let added = diff.iter().filter(|l| matches!(l, DiffLine::Added(_)));
+            let removed = diff.iter().filter(|l| matches!(l, DiffLine::Removed(_)));
+            format!(
+                "edited description (+{} −{})",
+                added.count(),
+                removed.count()
+            )
+        }
+        (Property::Tags, _) => {
+            let mut parts = vec![];
+            let added = entry.added_tags();
+            if !added.is_empty() {
+                parts.push(format!("added tag {}", added.join(", ")));
+            }
+            let removed = entry.removed_tags();
+            if !removed.is_empty() {
+                parts.push(format!("removed tag {}", removed.join(", ")));
+            }
+            if parts.is_empty() {
+                return None;
+            }
+            parts.join("; ")
+        }
+        (Property::Milestone, Some(milestone)) => format!("set milestone {}", milestone),
+        (Property::DueDate, Some(text)) => match OffsetDateTime::parse(text, &Rfc3339) {
+            Ok(date) => format!("set due date {}", date_format.format(&date)),
+            Err(_) => format!("set due date {}", text),
+        },
+        (Property::Comment(_), Some(_)) if old.is_none() => "added comment".to_owned(),
+        (Property::Comment(_), Some(_)) => "edited comment".to_owned(),
+        (Property::Field(name), Some(value)) => format!("set {} to {}", name, value),
+        (Property::Description, None) => "removed description".to_owned(),
+        (Property::Milestone, None) => "removed milestone".to_owned(),
+        (Property::DueDate, None) => "removed due date".to_owned(),
+        (Property::Comment(_), None) => "removed comment".to_owned(),
+        (Property::Field(name), None) => format!("removed {}", name),
+    };
+    Some(text)
+}
+
+fn execute(args: &Args, data: &DataSource, out: &mut dyn Write) -> Result<(), PosixError> {
     let mut issue = data.find(&args.issue_id)?;
 
-    println!("issue      {}", issue.id().id());
+    writeln!(out, "issue      {}", issue.id().id())?;
 
     issue.cache_cdate().expect("Cached CDate");
-    println!("Date       {}", issue.cdate());
+    writeln!(out, "Date       {}", args.date.format(issue.cdate()))?;
 
     issue.cache_milestone().expect("Cached Milestone");
     if let Some(milestone) = issue.milestone() {
-        println!("Milestone  {}", milestone);
+        writeln!(out, "Milestone  {}", milestone)?;
     }
 
     issue.cache_ddate().expect("Cached DDate");
     if let Some(ddate) = issue.ddate() {
-        println!("Due Date   {}", ddate);
+        writeln!(out, "Due Date   {}", args.date.format(ddate))?;
     }
 
     issue.cache_tags().expect("Cached Tags");
-    println!("Tags       {}", issue.tags().join(", "));
+    writeln!(out, "Tags       {}", issue.tags().join(", "))?;
 
-    println!();
+    writeln!(out)?;
 
     issue.cache_desc().expect("Cached Description");
     for line in issue.desc().lines() {
-        println!("    {}", line);
+        writeln!(out, "    {}", line)?;
     }
 
-    println!();
-
-    println!("Edit History:");
-    let dir_path = &issue.id().path(&data.issues_dir);
-    let files = &["description", "tags", "duedate", "milestone"];
-    let paths = files.map(|d| dir_path.join(d));
-    let _result = data
-        .repo
-        .git()
-        .args(&[
-            "log",
-            "-M",
-            "-C",
-            "-C",
-            "-C",
-            "--reverse",
-            "--format=* %>(16)%ah by %aN — %s",
-            "--",
-        ])
-        .args(paths)
-        .status();
+    writeln!(out)?;
+
+    writeln!(out, "Edit History:")?;
+    for entry in issue.history()? {
+        if let Some(summary) = summary(&entry, &args.date) {
+            let date = args.date.format(entry.date());
+            writeln!(
+                out,
+                "* {:>16} by {} — {}",
+                date,
+                entry.author_name(),
+                summary
+            )?;
+        }
+    }
 
     if args.comments {
-        println!();
+        writeln!(out)?;
         issue.cache_comments();
         for comment in issue.comments() {
-            println!("comment {}", comment.id());
-            println!("Author: {}", comment.author());
-            println!("Date    {}", comment.cdate());
+            writeln!(out, "comment {}", comment.id())?;
+            writeln!(out, "Author: {}", comment.author())?;
+            writeln!(out, "Date    {}", args.date.format(comment.cdate()))?;
             for line in comment.body().lines() {
-                println!();
-                println!("    {}", line);
-                println!();
+                writeln!(out)?;
+                writeln!(out, "    {}", line)?;
+                writeln!(out)?;
             }
         }
     }
@@ -105,7 +162,7 @@ fn main() {
     let args = Args::parse();
     cli_log::init_with_level(args.verbose.log_level_filter());
     log::debug!("Log Level is set to {}", log::max_level());
-    let data = match git_issue::DataSource::try_new(&args.git) {
+    let data = match git_issue::DataSource::try_new_at(&args.git, args.at.as_deref()) {
         Err(e) => {
             let err: PosixError = e.into();
             log::error!("{}", err);
@@ -114,8 +171,20 @@ fn main() {
         Ok(repo) => repo,
     };
 
-    if let Err(e) = execute(&args, &data) {
-        log::error!("{}", e);
-        std::process::exit(e.code());
+    let mut out = if args.no_pager {
+        Output::stdout()
+    } else {
+        Output::pager(&data.repo)
+    };
+    let result = execute(&args, &data, &mut out);
+    drop(out);
+    match result {
+        // The reader, e.g. the pager, went away
+        Err(e) if e.code() == posix_errors::EPIPE => {}
+        Err(e) => {
+            log::error!("{}", e);
+            std::process::exit(e.code());
+        }
+        Ok(_) => {}
     }
 }
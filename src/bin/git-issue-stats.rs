@@ -0,0 +1,340 @@
+#![allow(missing_docs)]
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+
+use clap::Parser;
+use clap_git_options::GitOptions;
+use clap_verbosity_flag::{Verbosity, WarnLevel};
+use time::{Duration, OffsetDateTime};
+
+use posix_errors::PosixError;
+
+use git_issue::{format_duration, DataSource, Id, State, StateChange};
+
+#[derive(Parser)]
+#[clap(
+    author,
+    version,
+    about = "Show issue statistics",
+    help_expected = true,
+    dont_collapse_args_in_usage = true
+)]
+struct Args {
+    /// Only count issues opened or closed after this date, e.g. "2 weeks ago" or 2022-10-01
+    #[clap(long, value_name = "DATE")]
+    since: Option<String>,
+
+    /// Only count issues opened or closed before this date
+    #[clap(long, value_name = "DATE")]
+    until: Option<String>,
+
+    /// Number of oldest open issues to list
+    #[clap(long, value_name = "N", default_value = "5")]
+    oldest: usize,
+
+    #[clap(flatten)]
+    git: GitOptions,
+
+    #[clap(flatten, next_help_heading = "OUTPUT")]
+    verbose: Verbosity<WarnLevel>,
+}
+
+struct Window {
+    since: Option<OffsetDateTime>,
+    until: OffsetDateTime,
+}
+
+impl Window {
+    fn contains(&self, date: &OffsetDateTime) -> bool {
+        self.since.is_none_or(|since| *date >= since) && *date <= self.until
+    }
+}
+
+#[derive(Default)]
+struct Lifetime {
+    opened: Option<OffsetDateTime>,
+    state: Option<State>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Counts {
+    open: usize,
+    opened: usize,
+    closed: usize,
+}
+
+#[derive(Debug, Default)]
+struct Stats {
+    /// Open & closed issues at the end of the window
+    open: usize,
+    closed: usize,
+    /// Issues opened & closed in the window
+    window: Counts,
+    /// Opened & closed per ISO week
+    weeks: BTreeMap<(i32, u8), Counts>,
+    time_to_close: Vec<Duration>,
+    /// Open issues ordered by opening date
+    oldest: Vec<(Id, OffsetDateTime)>,
+    tags: BTreeMap<String, Counts>,
+}
+
+impl Stats {
+    // arithmetic: counters are bounded by the number of state changes
+    #[allow(clippy::arithmetic_side_effects)]
+    fn new(changes: &[StateChange], window: &Window, tags: &HashMap<Id, Vec<String>>) -> Self {
+        let mut result = Self::default();
+        let mut lifetimes: HashMap<&Id, Lifetime> = HashMap::new();
+        let no_tags = vec![];
+        for change in changes.iter().filter(|c| *c.date() <= window.until) {
+            let lifetime = lifetimes.entry(change.id()).or_default();
+            lifetime.state = Some(*change.state());
+            let in_window = window.contains(change.date());
+            let issue_tags = tags.get(change.id()).unwrap_or(&no_tags);
+            match change.state() {
+                State::Open if lifetime.opened.is_none() => {
+                    lifetime.opened = Some(*change.date());
+                    if in_window {
+                        result.window.opened += 1;
+                        result.week(change.date()).opened += 1;
+                        for tag in issue_tags {
+                            result.tags.entry(tag.clone()).or_default().opened += 1;
+                        }
+                    }
+                }
+                State::Open => {}
+                State::Closed => {
+                    if in_window {
+                        result.window.closed += 1;
+                        result.week(change.date()).closed += 1;
+                        for tag in issue_tags {
+                            result.tags.entry(tag.clone()).or_default().closed += 1;
+                        }
+                        if let Some(opened) = lifetime.opened {
+                            result.time_to_close.push(*change.date() - opened);
+                        }
+                    }
+                }
+            }
+        }
+
+        for (id, lifetime) in lifetimes {
+            match lifetime.state {
+                Some(State::Open) => {
+                    result.open += 1;
+                    for tag in tags.get(id).unwrap_or(&no_tags) {
+                        result.tags.entry(tag.clone()).or_default().open += 1;
+                    }
+                    if let Some(opened) = lifetime.opened {
+                        result.oldest.push((id.clone(), opened));
+                    }
+                }
+                Some(State::Closed) => result.closed += 1,
+                None => {}
+            }
+        }
+        result
+            .oldest
+            .sort_by(|(a_id, a), (b_id, b)| a.cmp(b).then_with(|| a_id.id().cmp(b_id.id())));
+        result.time_to_close.sort_unstable();
+        result
+    }
+
+    fn week(&mut self, date: &OffsetDateTime) -> &mut Counts {
+        let (year, week, _) = date.to_iso_week_date();
+        self.weeks.entry((year, week)).or_default()
+    }
+
+    // arithmetic: the indices are within the bounds of `time_to_close`
+    #[allow(clippy::arithmetic_side_effects, clippy::integer_division)]
+    fn median_time_to_close(&self) -> Option<Duration> {
+        let len = self.time_to_close.len();
+        if len == 0 {
+            return None;
+        }
+        let upper = self.time_to_close[len / 2];
+        if len % 2 == 1 {
+            Some(upper)
+        } else {
+            Some((self.time_to_close[len / 2 - 1] + upper) / 2)
+        }
+    }
+}
+
+fn parse_date(data: &DataSource, text: &str) -> Result<OffsetDateTime, PosixError> {
+    git_issue::approxidate(&data.repo, text).ok_or_else(|| {
+        PosixError::new(
+            posix_errors::EINVAL,
+            format!("Failed to parse date “{}”", text),
+        )
+    })
+}
+
+fn execute(args: &Args, data: &DataSource, out: &mut dyn Write) -> Result<(), PosixError> {
+    let window = Window {
+        since: args
+            .since
+            .as_ref()
+            .map(|s| parse_date(data, s))
+            .transpose()?,
+        until: args
+            .until
+            .as_ref()
+            .map_or_else(|| Ok(OffsetDateTime::now_utc()), |s| parse_date(data, s))?,
+    };
+
+    let mut tags: HashMap<Id, Vec<String>> = HashMap::new();
+    for result in data.all() {
+        match result {
+            Ok(mut issue) => {
+                if let Err(e) = issue.cache_tags() {
+                    log::warn!("{}: {}", issue.id().short_id(), e);
+                    continue;
+                }
+                let issue_tags = issue
+                    .tags()
+                    .iter()
+                    .filter(|t| *t != "open" && *t != "closed")
+                    .cloned()
+                    .collect();
+                tags.insert(issue.id().clone(), issue_tags);
+            }
+            Err(e) => log::warn!("{}", e),
+        }
+    }
+
+    let stats = Stats::new(&data.state_changes()?, &window, &tags);
+
+    writeln!(out, "Open issues            {}", stats.open)?;
+    writeln!(out, "Closed issues          {}", stats.closed)?;
+    writeln!(out, "Opened                 {}", stats.window.opened)?;
+    writeln!(out, "Closed                 {}", stats.window.closed)?;
+    if let Some(median) = stats.median_time_to_close() {
+        writeln!(out, "Median time to close   {}", format_duration(median))?;
+    }
+
+    if !stats.weeks.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "{:<10} {:>7} {:>7}", "Week", "Opened", "Closed")?;
+        for ((year, week), counts) in &stats.weeks {
+            let label = format!("{}-W{:02}", year, week);
+            writeln!(
+                out,
+                "{:<10} {:>7} {:>7}",
+                label, counts.opened, counts.closed
+            )?;
+        }
+    }
+
+    if args.oldest > 0 && !stats.oldest.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "Oldest open issues")?;
+        for (id, opened) in stats.oldest.iter().take(args.oldest) {
+            let title = data.title(id).unwrap_or_default();
+            let age = format_duration(window.until - *opened);
+            writeln!(out, "{}  {:>10}  {}", id.short_id(), age, title)?;
+        }
+    }
+
+    if !stats.tags.is_empty() {
+        writeln!(out)?;
+        let width = stats.tags.keys().map(String::len).max().unwrap_or(0).max(3);
+        writeln!(
+            out,
+            "{:<width$} {:>7} {:>7} {:>7}",
+            "Tag",
+            "Open",
+            "Opened",
+            "Closed",
+            width = width
+        )?;
+        for (tag, counts) in &stats.tags {
+            writeln!(
+                out,
+                "{:<width$} {:>7} {:>7} {:>7}",
+                tag,
+                counts.open,
+                counts.opened,
+                counts.closed,
+                width = width
+            )?;
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(not(tarpaulin_include))]
+#[allow(clippy::exit)]
+fn main() {
+    let args = Args::parse();
+    cli_log::init_with_level(args.verbose.log_level_filter());
+    log::debug!("Log Level is set to {}", log::max_level());
+    let data = match git_issue::DataSource::try_new(&args.git) {
+        Err(e) => {
+            let err: PosixError = e.into();
+            log::error!("{}", err);
+            std::process::exit(err.code());
+        }
+        Ok(repo) => repo,
+    };
+
+    if let Err(e) = execute(&args, &data, &mut std::io::stdout().lock()) {
+        log::error!("{}", e);
+        std::process::exit(e.code());
+    }
+}
+
+#[cfg(test)]
+mod cmd_stats {
+    use clap::Parser;
+    use git_issue::DataSource;
+
+    fn stats(data: &DataSource, args: &[&str]) -> String {
+        let args = Parser::try_parse_from(args).expect("Parsed arguments");
+        let mut out: Vec<u8> = vec![];
+        crate::execute(&args, data, &mut out).expect("Computed statistics");
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn counts() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let tmp = tmp_dir.path();
+        git_wrapper::setup_test_author();
+        git_issue::create(tmp, false).unwrap();
+        let data = DataSource::try_from(tmp).unwrap();
+        let open = data
+            .create_issue("Open issue", vec!["bug".to_owned()], None)
+            .unwrap();
+        let closed = data
+            .create_issue("Closed issue", vec!["bug".to_owned()], None)
+            .unwrap();
+        data.close_issue(&closed).unwrap();
+
+        let actual = stats(&data, &["git-issue-stats"]);
+        let lines: Vec<&str> = actual.lines().collect();
+        assert_eq!(lines[0], "Open issues            1");
+        assert_eq!(lines[1], "Closed issues          1");
+        assert_eq!(lines[2], "Opened                 2");
+        assert_eq!(lines[3], "Closed                 1");
+        assert!(lines[4].starts_with("Median time to close   "));
+        assert!(actual.contains(&format!("{}  ", open.short_id())));
+        assert!(!actual.contains(&format!("{}  ", closed.short_id())));
+        assert_eq!(lines.last(), Some(&"bug       1       2       1"));
+    }
+
+    #[test]
+    fn window() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let tmp = tmp_dir.path();
+        git_wrapper::setup_test_author();
+        git_issue::create(tmp, false).unwrap();
+        let data = DataSource::try_from(tmp).unwrap();
+        data.create_issue("Open issue", vec![], None).unwrap();
+
+        let actual = stats(&data, &["git-issue-stats", "--until", "1 year ago"]);
+        assert!(actual.starts_with("Open issues            0\n"));
+        let actual2 = stats(&data, &["git-issue-stats", "--since", "1 hour ago"]);
+        assert!(actual2.contains("Opened                 1\n"));
+    }
+}
@@ -5,7 +5,7 @@ use clap_verbosity_flag::{Verbosity, WarnLevel};
 
 use posix_errors::PosixError;
 
-use git_issue::{DataSource, Id, WriteResult};
+use git_issue::{DataSource, Id, MergeStrategy, WriteResult};
 
 #[derive(Parser)]
 #[clap(
@@ -23,6 +23,22 @@ struct Args {
     #[clap(long_help = "One or multiple tags", required = true)]
     tags: Vec<String>,
 
+    #[clap(
+        long,
+        value_enum,
+        value_name = "STRATEGY",
+        long_help = "How the issue changes are committed, overrides issues.strategy in .issues/config"
+    )]
+    strategy: Option<MergeStrategy>,
+
+    #[clap(
+        long = "ref",
+        value_name = "REF",
+        long_help = "Commit the issue changes to this reference without touching the working \
+                     tree, e.g. refs/heads/issues, overrides issues.ref in .issues/config"
+    )]
+    reference: Option<String>,
+
     #[clap(flatten)]
     git: GitOptions,
 
@@ -76,45 +92,48 @@ fn remove_tags<'args>(
     Ok(applied)
 }
 
-fn execute(args: &Args, mut data: DataSource) -> Result<(), PosixError> {
+fn execute(args: &Args, data: DataSource) -> Result<(), PosixError> {
+    let data = match args.strategy {
+        Some(strategy) => data.with_strategy(strategy),
+        None => data,
+    };
+    let data = match &args.reference {
+        Some(reference) => data.with_ref(reference)?,
+        None => data,
+    };
     let id = data.find_issue(&args.issue_id).map_err(PosixError::from)?;
     log::info!("Starting transaction");
-    data.start_transaction().map_err(PosixError::from)?;
+    let transaction = data.transaction().map_err(PosixError::from)?;
 
-    let applied_tags_result = if args.remove {
-        remove_tags(&data, &id, &args.tags)
+    let applied = if args.remove {
+        remove_tags(&data, &id, &args.tags)?
     } else {
-        add_tags(&data, &id, &args.tags)
+        add_tags(&data, &id, &args.tags)?
     };
-    applied_tags_result
-        .map_err(PosixError::from)
-        .and_then(|applied| {
-            if applied.is_empty() {
-                log::warn!("Nothing to do");
-                log::info!("Rolling back transaction");
-                data.rollback_transaction().map_err(PosixError::from)
-            } else {
-                let word = if applied.len() > 1 { "tags" } else { "tag" };
-                let message = if args.remove {
-                    format!(
-                        "gi({}): Remove {}: {}",
-                        &id.short_id(),
-                        word,
-                        applied.join(", ")
-                    )
-                } else {
-                    format!(
-                        "gi({}): Add {}: {}",
-                        &id.short_id(),
-                        word,
-                        applied.join(", ")
-                    )
-                };
-
-                log::info!("Committing transaction");
-                data.finish_transaction(&message).map_err(PosixError::from)
-            }
-        })
+    if applied.is_empty() {
+        log::warn!("Nothing to do");
+        log::info!("Rolling back transaction");
+        return transaction.rollback().map_err(PosixError::from);
+    }
+    let word = if applied.len() > 1 { "tags" } else { "tag" };
+    let message = if args.remove {
+        format!(
+            "gi({}): Remove {}: {}",
+            &id.short_id(),
+            word,
+            applied.join(", ")
+        )
+    } else {
+        format!(
+            "gi({}): Add {}: {}",
+            &id.short_id(),
+            word,
+            applied.join(", ")
+        )
+    };
+
+    log::info!("Committing transaction");
+    transaction.commit(&message).map_err(PosixError::from)
 }
 
 #[cfg(not(tarpaulin_include))]
@@ -199,6 +218,61 @@ mod cmd_tag {
         }
     }
 
+    #[test]
+    fn squash_strategy() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let tmp = tmp_dir.path();
+        let id = prepare(tmp, &[]);
+        let data = DataSource::try_from(tmp).unwrap();
+        let start = data.repo.head();
+        let args = Parser::try_parse_from([
+            "git-issue-tag",
+            "--strategy",
+            "squash",
+            id.id(),
+            "foo",
+            "bar",
+        ])
+        .expect("Parsed arguments");
+        crate::execute(&args, data).unwrap();
+
+        let data = DataSource::try_from(tmp).unwrap();
+        assert_eq!(data.tags(&id), ["bar", "foo", "open"]);
+        let out = data
+            .repo
+            .git()
+            .args(["log", "--format=%s", &format!("{}..HEAD", start)])
+            .output()
+            .unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&out.stdout).trim(),
+            format!("gi({}): Add tags: foo, bar", id.short_id())
+        );
+    }
+
+    #[test]
+    fn dedicated_ref() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let tmp = tmp_dir.path();
+        let id = prepare(tmp, &[]);
+        let data = DataSource::try_from(tmp).unwrap();
+        let head = data.repo.head();
+        let args = [
+            "git-issue-tag",
+            "--ref",
+            "refs/heads/issues",
+            id.id(),
+            "foo",
+        ];
+        crate::execute(&Parser::try_parse_from(args).unwrap(), data).unwrap();
+
+        let data = DataSource::try_from(tmp).unwrap();
+        assert_eq!(data.repo.head(), head, "HEAD is untouched");
+        assert_eq!(data.tags(&id), ["open"]);
+        let data = data.with_ref("refs/heads/issues").unwrap();
+        assert_eq!(data.tags(&id), ["foo", "open"]);
+    }
+
     #[test]
     fn remove_tag() {
         let tmp_dir = tempfile::TempDir::new().unwrap();
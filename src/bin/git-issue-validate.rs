@@ -1,6 +1,6 @@
 #![allow(missing_docs)]
+use std::fs;
 use std::path::Path;
-use std::{fs, process::Command};
 
 use clap::Parser;
 use clap_git_options::GitOptions;
@@ -29,7 +29,7 @@ struct Args {
     git: GitOptions,
 }
 
-fn validate_issue(id: &Id, path: &Path, fix: bool) -> Result<bool, PosixError> {
+fn validate_issue(data: &DataSource, id: &Id, path: &Path, fix: bool) -> Result<bool, PosixError> {
     log::info!("Validating issue: {}", id.short_id());
     let mut result = true;
 
@@ -57,18 +57,20 @@ fn validate_issue(id: &Id, path: &Path, fix: bool) -> Result<bool, PosixError> {
         }
     }
 
-    let out = Command::new("git")
-        .args(&["rev-list", "--quiet", "-1", id.id(), "--"])
-        .output()?;
-    if !out.status.success() {
+    // The id has to be the commit creating the issue
+    if data.creation_infos([id])?.is_empty() {
         let expected = {
-            let out_child = Command::new("git")
-                .args(&["rev-list", "--reverse", "-1", "HEAD", "--"])
+            let out_child = data
+                .repo
+                .git()
+                .args(["rev-list", "--reverse", "-1", "HEAD", "--"])
                 .arg(path.join("description"))
                 .output()?;
             let child_commit = String::from_utf8_lossy(&out_child.stdout);
-            let out_parent = Command::new("git")
-                .args(&[
+            let out_parent = data
+                .repo
+                .git()
+                .args([
                     "rev-list",
                     "-1",
                     &format!("{}^1", child_commit.trim()),
@@ -96,7 +98,9 @@ fn validate_issue(id: &Id, path: &Path, fix: bool) -> Result<bool, PosixError> {
             let dst_dir = expected.path(parent_dir);
             fs::create_dir_all(dst_dir.parent().expect("prefix dir"))?;
             log::warn!("Moving to {:?}", dst_dir);
-            let _status = Command::new("git")
+            let _status = data
+                .repo
+                .git()
                 .arg("mv")
                 .args(&[src_dir, dst_dir])
                 .status()?;
@@ -123,7 +127,7 @@ fn validate(data: &DataSource, fix: bool) -> Result<bool, PosixError> {
                 if prefix_dir_entry.file_type()?.is_dir() {
                     let path = dir_entry.path();
                     let id = Id::from(dir_entry);
-                    if !validate_issue(&id, &path, fix)? {
+                    if !validate_issue(data, &id, &path, fix)? {
                         result = false;
                     }
                 }
@@ -1,4 +1,12 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
 use git_wrapper::{PosixError, EINVAL};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::id::Id;
 
 pub type Cache<T> = Option<T>;
 
@@ -21,3 +29,168 @@ impl From<CacheError> for PosixError {
         }
     }
 }
+
+/// First line of the on-disk cache, files with another header are ignored
+const DISK_CACHE_HEADER: &str = "# git-issue cache v1";
+
+/// Parsed metadata of an issue as stored in the on-disk cache
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Metadata {
+    /// Git tree sha of the issue directory the metadata was read from
+    pub tree: String,
+    pub cdate: OffsetDateTime,
+    pub author_name: String,
+    pub author_email: String,
+    pub comment_count: usize,
+    pub milestone: Option<String>,
+    pub title: String,
+    pub tags: Vec<String>,
+}
+
+/// Issue metadata persisted across invocations, usually in `.git/git-issue/cache`
+///
+/// An entry is only valid as long as the tree sha of the issue directory matches. The file
+/// has one tab separated line per issue: id, tree, creation date, author name & email,
+/// comment count, milestone, title and the tags.
+#[derive(Debug)]
+pub(crate) struct DiskCache {
+    path: PathBuf,
+    entries: HashMap<Id, Metadata>,
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+}
+
+fn unescape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => result.push('\t'),
+            Some('n') => result.push('\n'),
+            Some(other) => result.push(other),
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+fn parse_line(line: &str) -> Option<(Id, Metadata)> {
+    let mut fields = line.split('\t');
+    let id = Id::new(fields.next()?.to_owned());
+    let tree = fields.next()?.to_owned();
+    let cdate = OffsetDateTime::parse(fields.next()?, &Rfc3339).ok()?;
+    let author_name = unescape(fields.next()?);
+    let author_email = unescape(fields.next()?);
+    let comment_count = fields.next()?.parse().ok()?;
+    // The milestone is prefixed with `=`, an empty field means no milestone
+    let milestone = fields.next()?.strip_prefix('=').map(unescape);
+    let title = unescape(fields.next()?);
+    let tags = fields.map(unescape).collect();
+    Some((
+        id,
+        Metadata {
+            tree,
+            cdate,
+            author_name,
+            author_email,
+            comment_count,
+            milestone,
+            title,
+            tags,
+        },
+    ))
+}
+
+fn format_line(id: &Id, metadata: &Metadata) -> Option<String> {
+    let mut fields = vec![
+        id.id().clone(),
+        metadata.tree.clone(),
+        metadata.cdate.format(&Rfc3339).ok()?,
+        escape(&metadata.author_name),
+        escape(&metadata.author_email),
+        metadata.comment_count.to_string(),
+        metadata
+            .milestone
+            .as_ref()
+            .map_or_else(String::new, |m| format!("={}", escape(m))),
+        escape(&metadata.title),
+    ];
+    fields.extend(metadata.tags.iter().map(|t| escape(t)));
+    Some(fields.join("\t"))
+}
+
+impl DiskCache {
+    /// Read the cache file, a missing or unreadable file results in an empty cache
+    pub fn load(path: PathBuf) -> Self {
+        let entries = match std::fs::read_to_string(&path) {
+            Ok(text) if text.lines().next() == Some(DISK_CACHE_HEADER) => {
+                text.lines().skip(1).filter_map(parse_line).collect()
+            }
+            Ok(_) => {
+                log::debug!("Ignoring cache {:?} with unknown format", path);
+                HashMap::new()
+            }
+            Err(_) => HashMap::new(),
+        };
+        Self { path, entries }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Return the metadata of an issue if it was cached for the given tree
+    pub fn get(&self, id: &Id, tree: &str) -> Option<&Metadata> {
+        self.entries.get(id).filter(|m| m.tree == tree)
+    }
+
+    /// Add or replace entries and drop the ones of issues missing from `trees`, the file is only
+    /// rewritten if anything changed
+    ///
+    /// `trees` are the tree shas of all current issues, see [`crate::DataSource::trees`].
+    /// Entries of deleted or moved issues are removed this way.
+    ///
+    /// # Errors
+    ///
+    /// Will throw an error if the file can not be written
+    pub fn update(
+        &mut self,
+        entries: HashMap<Id, Metadata>,
+        trees: &HashMap<Id, String>,
+    ) -> std::io::Result<()> {
+        let total = self.entries.len();
+        self.entries.retain(|id, _| trees.contains_key(id));
+        let changed = self.entries.len() != total
+            || entries
+                .iter()
+                .any(|(id, metadata)| self.entries.get(id) != Some(metadata));
+        if !changed {
+            return Ok(());
+        }
+        self.entries.extend(entries);
+        let mut ids: Vec<&Id> = self.entries.keys().collect();
+        ids.sort_unstable_by(|a, b| a.id().cmp(b.id()));
+        let mut text = format!("{}\n", DISK_CACHE_HEADER);
+        for id in ids {
+            if let Some(line) = format_line(id, &self.entries[id]) {
+                text.push_str(&line);
+                text.push('\n');
+            }
+        }
+        let dir = self.path.parent().unwrap_or_else(|| Path::new("."));
+        std::fs::create_dir_all(dir)?;
+        // Write to a unique file & rename, so concurrent readers never see a partial file and
+        // concurrent writers do not write to the same file
+        let mut tmp = tempfile::Builder::new().prefix("cache.").tempfile_in(dir)?;
+        tmp.write_all(text.as_bytes())?;
+        tmp.persist(&self.path).map(drop).map_err(|e| e.error)
+    }
+}
@@ -0,0 +1,194 @@
+//! Stage several property changes and commit them at once
+
+#[cfg(not(feature = "strict-compatibility"))]
+use std::collections::HashSet;
+
+use crate::errors::WriteError;
+#[cfg(not(feature = "strict-compatibility"))]
+use crate::source::{change, marker, subject};
+use crate::source::{Action, ChangeAction, CommitProperty};
+use crate::{DataSource, Id, WriteResult};
+
+/// Property changes to one or more issues which are committed together
+///
+/// Every staged change is checked when the set is committed, so redundant changes, e.g. adding
+/// a tag an issue already has, are skipped like with [`DataSource::add_tag`]. With the
+/// `strict-compatibility` feature each change is committed on its own, like git-issue(1) does.
+///
+/// ```no_run
+/// # use git_issue::DataSource;
+/// # fn example(data: &DataSource, id: &git_issue::Id) -> Result<(), git_issue::WriteError> {
+/// data.change_set()
+///     .add_tag(id, "bug")
+///     .add_milestone(id, "v1.0")
+///     .commit()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+#[must_use = "Changes are only written on commit"]
+pub struct ChangeSet<'src> {
+    src: &'src DataSource,
+    changes: Vec<(Id, CommitProperty)>,
+}
+
+impl<'src> ChangeSet<'src> {
+    pub(crate) const fn new(src: &'src DataSource) -> Self {
+        Self {
+            src,
+            changes: vec![],
+        }
+    }
+
+    fn stage(mut self, id: &Id, property: CommitProperty) -> Self {
+        self.changes.push((id.clone(), property));
+        self
+    }
+
+    /// Set the description of a new issue and tag it as open
+    #[inline]
+    pub fn new_description(self, id: &Id, text: &str) -> Self {
+        let description = CommitProperty::Description {
+            action: ChangeAction::New,
+            id: id.id().clone(),
+            description: text.to_owned(),
+        };
+        self.stage(id, description).add_tag(id, "open")
+    }
+
+    /// Replace the description of an issue
+    #[inline]
+    pub fn edit_description(self, id: &Id, text: &str) -> Self {
+        let description = CommitProperty::Description {
+            action: ChangeAction::Edit,
+            id: id.id().clone(),
+            description: text.to_owned(),
+        };
+        self.stage(id, description)
+    }
+
+    /// Add a tag to an issue
+    #[inline]
+    pub fn add_tag(self, id: &Id, tag: &str) -> Self {
+        let property = CommitProperty::Tag {
+            action: Action::Add,
+            tag: tag.to_owned(),
+        };
+        self.stage(id, property)
+    }
+
+    /// Remove a tag from an issue
+    #[inline]
+    pub fn remove_tag(self, id: &Id, tag: &str) -> Self {
+        let property = CommitProperty::Tag {
+            action: Action::Remove,
+            tag: tag.to_owned(),
+        };
+        self.stage(id, property)
+    }
+
+    /// Set the milestone of an issue
+    #[inline]
+    pub fn add_milestone(self, id: &Id, milestone: &str) -> Self {
+        let property = CommitProperty::Milestone {
+            action: Action::Add,
+            milestone: milestone.to_owned(),
+        };
+        self.stage(id, property)
+    }
+
+    /// Remove the milestone of an issue
+    #[inline]
+    pub fn remove_milestone(self, id: &Id) -> Self {
+        // The current milestone is looked up on commit
+        let property = CommitProperty::Milestone {
+            action: Action::Remove,
+            milestone: String::new(),
+        };
+        self.stage(id, property)
+    }
+
+    /// Return `true` if no changes are staged
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Write all staged changes and commit them with a combined message
+    ///
+    /// A single change gets the same commit message as if it was committed on its own.
+    ///
+    /// # Errors
+    ///
+    /// Will throw error on failure to do IO or commiting
+    #[inline]
+    pub fn commit(self) -> Result<WriteResult, WriteError> {
+        let mut applied = vec![];
+        for (id, property) in self.changes {
+            let property = match effective(self.src, &id, property) {
+                Some(property) => property,
+                None => continue,
+            };
+            #[cfg(feature = "strict-compatibility")]
+            self.src.write(&id, &property)?;
+            #[cfg(not(feature = "strict-compatibility"))]
+            self.src.write_to_file(&id, &property)?;
+            applied.push((id, property));
+        }
+        if applied.is_empty() {
+            return Ok(WriteResult::NoChanges);
+        }
+        #[cfg(not(feature = "strict-compatibility"))]
+        self.src.commit_written(&message(&applied))?;
+        Ok(WriteResult::Applied)
+    }
+}
+
+/// Return the change as it applies to the current data, `None` if it would change nothing
+fn effective(src: &DataSource, id: &Id, property: CommitProperty) -> Option<CommitProperty> {
+    match &property {
+        CommitProperty::Description { .. } => Some(property),
+        CommitProperty::Tag { action, tag } => {
+            let present = src.tags(id).contains(tag);
+            match action {
+                Action::Add => (!present).then_some(property),
+                Action::Remove => present.then_some(property),
+            }
+        }
+        CommitProperty::Milestone {
+            action: Action::Add,
+            milestone,
+        } => (src.milestone(id).as_ref() != Some(milestone)).then_some(property),
+        CommitProperty::Milestone {
+            action: Action::Remove,
+            ..
+        } => src
+            .milestone(id)
+            .map(|milestone| CommitProperty::Milestone {
+                action: Action::Remove,
+                milestone,
+            }),
+    }
+}
+
+/// Combine the messages of all changes
+///
+/// The subject lists the changes if they all belong to the same issue, the body contains the
+/// marker line of every change.
+#[cfg(not(feature = "strict-compatibility"))]
+fn message(changes: &[(Id, CommitProperty)]) -> String {
+    if let [(id, property)] = changes {
+        return format!("{}\n\n{}", subject(id, property), marker(property));
+    }
+    let ids: HashSet<&Id> = changes.iter().map(|(id, _)| id).collect();
+    let title = match ids.iter().next() {
+        Some(id) if ids.len() == 1 => {
+            let list: Vec<String> = changes.iter().map(|(_, p)| change(p)).collect();
+            format!("gi({}): {}", id.short_id(), list.join(", "))
+        }
+        _ => format!("gi: Update {} issues", ids.len()),
+    };
+    let markers: Vec<String> = changes.iter().map(|(_, p)| marker(p)).collect();
+    format!("{}\n\n{}", title, markers.join("\n"))
+}
@@ -0,0 +1,108 @@
+use std::io::IsTerminal;
+
+/// Escape sequence resetting all colours & attributes
+pub(crate) const RESET: &str = "\x1b[m";
+pub(crate) const BOLD_RED: &str = "\x1b[1;31m";
+pub(crate) const CYAN: &str = "\x1b[36m";
+pub(crate) const DIM: &str = "\x1b[2m";
+
+/// When to colour the output
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ColorChoice {
+    /// Always colour the output
+    Always,
+    /// Never colour the output
+    Never,
+    /// Colour the output if stdout is a terminal and `NO_COLOR` is not set
+    #[default]
+    Auto,
+}
+
+impl ColorChoice {
+    /// Return `true` if the output should be coloured
+    #[inline]
+    #[must_use]
+    pub fn enabled(self) -> bool {
+        match self {
+            Self::Always => true,
+            Self::Never => false,
+            Self::Auto => {
+                let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+                !no_color && std::io::stdout().is_terminal()
+            }
+        }
+    }
+}
+
+/// Parse a git-config(1) like colour specification e.g. `bold red` into an escape sequence.
+///
+/// The first colour is used as foreground, the second one as background colour.
+pub(crate) fn parse(spec: &str) -> Option<String> {
+    let mut codes: Vec<String> = vec![];
+    let mut colors = 0_u8;
+    for word in spec.split_whitespace() {
+        if word == "reset" {
+            return Some(RESET.to_owned());
+        }
+        let attribute = match word {
+            "bold" => Some(1),
+            "dim" => Some(2),
+            "italic" => Some(3),
+            "ul" | "underline" => Some(4),
+            "blink" => Some(5),
+            "reverse" => Some(7),
+            "strike" => Some(9),
+            _ => None,
+        };
+        if let Some(code) = attribute {
+            codes.push(code.to_string());
+            continue;
+        }
+
+        // arithmetic: `color` is at most 7 and `colors` at most 1, so values stay below 108
+        #[allow(clippy::arithmetic_side_effects)]
+        let code = {
+            let offset = if colors == 0 { 0 } else { 10 };
+            let (bright, name) = word
+                .strip_prefix("bright")
+                .map_or((false, word), |name| (true, name));
+            let color = match name {
+                "black" => Some(0),
+                "red" => Some(1),
+                "green" => Some(2),
+                "yellow" => Some(3),
+                "blue" => Some(4),
+                "magenta" => Some(5),
+                "cyan" => Some(6),
+                "white" => Some(7),
+                "default" if !bright => Some(9),
+                _ => None,
+            };
+            match (color, bright) {
+                (Some(c), false) => format!("{}", 30 + offset + c),
+                (Some(c), true) => format!("{}", 90 + offset + c),
+                (None, _) => {
+                    let n: u8 = word.parse().ok()?;
+                    format!("{};5;{}", 38 + offset, n)
+                }
+            }
+        };
+        if colors >= 2 {
+            return None;
+        }
+        colors = colors.saturating_add(1);
+        codes.push(code);
+    }
+    if codes.is_empty() {
+        return None;
+    }
+    Some(format!("\x1b[{}m", codes.join(";")))
+}
+
+/// Wrap text in the colour escape sequence
+pub(crate) fn paint(color: &str, text: &str) -> String {
+    if text.is_empty() {
+        return String::new();
+    }
+    format!("{}{}{}", color, text, RESET)
+}
@@ -0,0 +1,321 @@
+use std::fmt::Write;
+use std::str::FromStr;
+
+use git_wrapper::Repository;
+use time::format_description::well_known::Rfc3339;
+use time::{Duration, OffsetDateTime, UtcOffset};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Style {
+    Default,
+    Iso,
+    IsoStrict,
+    Relative,
+    Rfc,
+    Short,
+    Unix,
+    Format(String),
+}
+
+/// Date rendering mode, mirroring `git log --date=…`
+///
+/// Supported modes are `default`, `iso`, `iso-strict`, `rfc`, `short`, `relative`, `unix` and
+/// `format:<strftime>`. Appending `-local` (or using `local` alone) converts dates to the local
+/// timezone before rendering them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DateFormat {
+    style: Style,
+    local: Option<UtcOffset>,
+}
+
+impl Default for DateFormat {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            style: Style::Default,
+            local: None,
+        }
+    }
+}
+
+impl FromStr for DateFormat {
+    type Err = String;
+
+    #[inline]
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if let Some(fmt) = value.strip_prefix("format-local:") {
+            return Ok(Self {
+                style: Style::Format(fmt.to_owned()),
+                local: Some(local_offset()),
+            });
+        }
+        if let Some(fmt) = value.strip_prefix("format:") {
+            return Ok(Self {
+                style: Style::Format(fmt.to_owned()),
+                local: None,
+            });
+        }
+        let (mode, local) = match value.strip_suffix("-local") {
+            Some(mode) => (mode, true),
+            None if value == "local" => ("default", true),
+            None => (value, false),
+        };
+        let style = match mode {
+            "default" => Style::Default,
+            "iso" | "iso8601" => Style::Iso,
+            "iso-strict" | "iso8601-strict" => Style::IsoStrict,
+            "relative" => Style::Relative,
+            "rfc" | "rfc2822" => Style::Rfc,
+            "short" => Style::Short,
+            "unix" => Style::Unix,
+            _ => return Err(format!("Unknown date format “{}”", value)),
+        };
+        Ok(Self {
+            style,
+            local: local.then(local_offset),
+        })
+    }
+}
+
+/// Return the local timezone offset, falling back to UTC if it can not be determined
+fn local_offset() -> UtcOffset {
+    UtcOffset::current_local_offset().unwrap_or_else(|e| {
+        log::debug!("Using UTC as local timezone: {}", e);
+        UtcOffset::UTC
+    })
+}
+
+impl DateFormat {
+    /// Render the date
+    #[inline]
+    #[must_use]
+    pub fn format(&self, date: &OffsetDateTime) -> String {
+        let date = self.local.map_or(*date, |offset| date.to_offset(offset));
+        match &self.style {
+            Style::Default => strftime(&date, "%a %b %-d %H:%M:%S %Y %z"),
+            Style::Iso => strftime(&date, "%Y-%m-%d %H:%M:%S %z"),
+            Style::IsoStrict => date.format(&Rfc3339).unwrap_or_default(),
+            Style::Relative => relative(&date, &OffsetDateTime::now_utc()),
+            Style::Rfc => strftime(&date, "%a, %-d %b %Y %H:%M:%S %z"),
+            Style::Short => strftime(&date, "%Y-%m-%d"),
+            Style::Unix => date.unix_timestamp().to_string(),
+            Style::Format(fmt) => strftime(&date, fmt),
+        }
+    }
+}
+
+/// Return a human readable duration like `3 days` or `2 months`
+#[inline]
+#[must_use]
+pub fn format_duration(duration: Duration) -> String {
+    span(duration.whole_seconds().saturating_abs())
+}
+
+/// Parse a duration like `7d`, `2w`, `36h` or `1w2d`
+///
+/// Supported units are `s`, `m`, `h`, `d` and `w`.
+///
+/// # Errors
+///
+/// Returns an error message if the text is not a valid duration
+#[inline]
+pub fn parse_duration(text: &str) -> Result<Duration, String> {
+    let invalid = || format!("Invalid duration “{}”, expected e.g. 7d or 2w", text);
+    let mut result = Duration::ZERO;
+    let mut number = String::new();
+    for c in text.trim().chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let n: i64 = number.parse().map_err(|_err| invalid())?;
+        number.clear();
+        let unit: i64 = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86_400,
+            'w' => 604_800,
+            _ => return Err(invalid()),
+        };
+        // Duration::weeks() & co. panic on overflow
+        let seconds = n.checked_mul(unit).ok_or_else(invalid)?;
+        result = result
+            .checked_add(Duration::seconds(seconds))
+            .ok_or_else(invalid)?;
+    }
+    if !number.is_empty() || result.is_zero() {
+        return Err(invalid());
+    }
+    Ok(result)
+}
+
+/// Parse a date the way git(1) does for `--since` & `--until`, e.g. `2 weeks ago` or `2022-10-01`
+///
+/// Returns `None` if git does not understand the date.
+#[inline]
+#[must_use]
+pub fn approxidate(repo: &Repository, text: &str) -> Option<OffsetDateTime> {
+    let out = repo
+        .git()
+        .args(["rev-parse", &format!("--since={}", text)])
+        .output()
+        .ok()?;
+    let output = String::from_utf8_lossy(&out.stdout);
+    let timestamp: i64 = output.trim().strip_prefix("--max-age=")?.parse().ok()?;
+    OffsetDateTime::from_unix_timestamp(timestamp).ok()
+}
+
+/// Return a human readable time span like `3 days`
+// arithmetic: the rounding additions are applied to values far below i64::MAX
+#[allow(clippy::arithmetic_side_effects, clippy::integer_division)]
+fn span(seconds: i64) -> String {
+    let plural = |n: i64, unit: &str| {
+        if n == 1 {
+            format!("1 {}", unit)
+        } else {
+            format!("{} {}s", n, unit)
+        }
+    };
+    if seconds < 90 {
+        return plural(seconds, "second");
+    }
+    let minutes = (seconds + 30) / 60;
+    if minutes < 90 {
+        return plural(minutes, "minute");
+    }
+    let hours = (minutes + 30) / 60;
+    if hours < 36 {
+        return plural(hours, "hour");
+    }
+    let days = (hours + 12) / 24;
+    if days < 14 {
+        return plural(days, "day");
+    }
+    if days < 70 {
+        return plural((days + 3) / 7, "week");
+    }
+    if days < 365 {
+        return plural((days + 15) / 30, "month");
+    }
+    plural((days + 183) / 365, "year")
+}
+
+/// Return the date relative to `now`, like `3 days ago` or `in 2 hours`
+pub(crate) fn relative(date: &OffsetDateTime, now: &OffsetDateTime) -> String {
+    let seconds = (*now - *date).whole_seconds();
+    if seconds < 0 {
+        format!("in {}", span(seconds.saturating_neg()))
+    } else {
+        format!("{} ago", span(seconds))
+    }
+}
+
+/// Return the due date relative to `now`, like `due in 2 days` or `overdue by 5 days`
+pub(crate) fn due_relative(date: &OffsetDateTime, now: &OffsetDateTime) -> String {
+    let seconds = (*date - *now).whole_seconds();
+    if seconds < 0 {
+        format!("overdue by {}", span(seconds.saturating_neg()))
+    } else {
+        format!("due in {}", span(seconds))
+    }
+}
+
+/// Render a date with a strftime(3) like format string
+///
+/// A `-` after the `%` disables padding of numeric values, e.g. `%-d`.
+#[allow(clippy::too_many_lines)]
+fn strftime(date: &OffsetDateTime, fmt: &str) -> String {
+    let mut result = String::new();
+    let mut chars = fmt.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            result.push(c);
+            continue;
+        }
+        let pad = if chars.peek() == Some(&'-') {
+            chars.next();
+            false
+        } else {
+            true
+        };
+        let number = |n: u32, width: usize| {
+            if pad {
+                format!("{:0width$}", n, width = width)
+            } else {
+                n.to_string()
+            }
+        };
+        // Writing to a String never fails
+        #[allow(unused_must_use)]
+        match chars.next() {
+            Some('Y') => {
+                write!(result, "{}", date.year());
+            }
+            Some('y') => result.push_str(&number(date.year().rem_euclid(100).unsigned_abs(), 2)),
+            Some('m') => result.push_str(&number(u8::from(date.month()).into(), 2)),
+            Some('d') => result.push_str(&number(date.day().into(), 2)),
+            Some('e') => {
+                write!(result, "{:>2}", date.day());
+            }
+            Some('j') => result.push_str(&number(date.ordinal().into(), 3)),
+            Some('H') => result.push_str(&number(date.hour().into(), 2)),
+            Some('I') => {
+                let hour = match date.hour() % 12 {
+                    0 => 12,
+                    h => h,
+                };
+                result.push_str(&number(hour.into(), 2));
+            }
+            Some('M') => result.push_str(&number(date.minute().into(), 2)),
+            Some('S') => result.push_str(&number(date.second().into(), 2)),
+            Some('p') => result.push_str(if date.hour() < 12 { "AM" } else { "PM" }),
+            Some('a') => result.push_str(&date.weekday().to_string()[..3]),
+            Some('A') => {
+                write!(result, "{}", date.weekday());
+            }
+            Some('b' | 'h') => result.push_str(&date.month().to_string()[..3]),
+            Some('B') => {
+                write!(result, "{}", date.month());
+            }
+            Some('u') => {
+                write!(result, "{}", date.weekday().number_from_monday());
+            }
+            Some('w') => {
+                write!(result, "{}", date.weekday().number_days_from_sunday());
+            }
+            Some('V') => result.push_str(&number(date.iso_week().into(), 2)),
+            Some('z') => {
+                let (h, m, _) = date.offset().as_hms();
+                let sign = if date.offset().is_negative() {
+                    '-'
+                } else {
+                    '+'
+                };
+                write!(
+                    result,
+                    "{}{:02}{:02}",
+                    sign,
+                    h.unsigned_abs(),
+                    m.unsigned_abs()
+                );
+            }
+            Some('s') => {
+                write!(result, "{}", date.unix_timestamp());
+            }
+            Some('F') => result.push_str(&strftime(date, "%Y-%m-%d")),
+            Some('T') => result.push_str(&strftime(date, "%H:%M:%S")),
+            Some('R') => result.push_str(&strftime(date, "%H:%M")),
+            Some('D') => result.push_str(&strftime(date, "%m/%d/%y")),
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('%') => result.push('%'),
+            Some(x) => {
+                result.push('%');
+                result.push(x);
+            }
+            None => result.push('%'),
+        }
+    }
+    result
+}
@@ -1,7 +1,10 @@
+use std::path::PathBuf;
+
+use getset::Getters;
 use git_wrapper::{CommitError, StagingError, StashingError};
 use posix_errors::PosixError;
 
-use crate::{Id, E_ISSUES_DIR_EXIST, E_REPO_BARE, E_REPO_EXIST, E_STASH_ERROR};
+use crate::{CacheError, Id, E_ISSUES_DIR_EXIST, E_REPO_BARE, E_REPO_EXIST, E_STASH_ERROR};
 
 /// Failure to find an issue
 #[derive(thiserror::Error, Debug)]
@@ -21,6 +24,34 @@ impl From<FindError> for PosixError {
     }
 }
 
+/// An issue which was skipped, because it could not be loaded
+#[derive(thiserror::Error, Debug, Getters)]
+#[error("{}: {cause}", path.display())]
+#[getset(get = "pub")]
+pub struct IssueLoadError {
+    /// Path of the issue directory or of the directory which could not be listed
+    path: PathBuf,
+    /// Why the issue was skipped
+    #[source]
+    cause: CacheError,
+}
+
+impl IssueLoadError {
+    /// Create new instance
+    #[inline]
+    #[must_use]
+    pub const fn new(path: PathBuf, cause: CacheError) -> Self {
+        Self { path, cause }
+    }
+}
+
+impl From<IssueLoadError> for PosixError {
+    #[inline]
+    fn from(e: IssueLoadError) -> Self {
+        Self::new(posix_errors::EIO, format!("{}", e))
+    }
+}
+
 /// Error during `DataSource` initialization
 #[derive(thiserror::Error, Debug)]
 pub enum InitError {
@@ -30,6 +61,12 @@ pub enum InitError {
     /// No `.issues/` directory found
     #[error("Not an issues repository (or any of the parent directories)")]
     IssuesRepoNotFound,
+    /// The revision given to `--at` can not be resolved
+    #[error("Unknown revision {0}")]
+    RevisionNotFound(String),
+    /// The reference given to `--ref` is checked out, committing to it would desync the index
+    #[error("Can not commit issues to {0}, it is checked out")]
+    CheckedOut(String),
 }
 
 /// Writing an issue property failed
@@ -41,6 +78,9 @@ pub enum WritePropertyError {
     /// IO Failure
     #[error("{0}")]
     IoError(#[from] std::io::Error),
+    /// Issues are read from a revision
+    #[error("Can not change issues read from revision {0}")]
+    ReadOnly(String),
 }
 
 /// Failure to write issue changes
@@ -66,6 +106,9 @@ pub enum RollbackError {
     #[error("Failed to reset back to commit {0}.\nTo restore your data use:\n git reset --hard {0} && git stash pop")]
     /// Failed to reset HEAD and unstash.
     ResetUnstash(String),
+    /// Failed to reset the issue reference
+    #[error("{0}\nFailed to roll back {1}.\nUse git reflog {1} to restore it.")]
+    UpdateRef(String, String),
 }
 
 /// Failed to commit transaction.
@@ -86,6 +129,23 @@ pub enum FinishError {
     /// Failed to merge not fast forward branch
     #[error("Failed to reset back to commit {0}.\nTo restore your data repo to previous state:\n git reset --hard {0} && git stash pop")]
     Merge(String),
+    /// Failed to squash the commits, the transaction is rolled back
+    #[error("{0}\nFailed to squash issue changes, they are rolled back.")]
+    Squash(String),
+}
+
+/// Failure to read the issue history
+#[derive(thiserror::Error, Debug)]
+pub enum HistoryError {
+    /// Failed to execute git(1)
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    /// git(1) exited with an error or produced unexpected output
+    #[error("{0}")]
+    Git(String),
+    /// Failed to parse a commit date
+    #[error("{0}")]
+    ParseError(#[from] time::error::Parse),
 }
 
 /// Error during starting a transaction
@@ -119,6 +179,7 @@ impl From<WritePropertyError> for PosixError {
                 }
                 StagingError::Failure(msg, code) => Self::new(code, msg),
             },
+            WritePropertyError::ReadOnly(_) => Self::new(posix_errors::EROFS, format!("{}", e)),
         }
     }
 }
@@ -155,6 +216,19 @@ impl From<InitError> for PosixError {
         match e {
             InitError::GitRepoNotFound => Self::new(E_REPO_EXIST, format!("{}", e)),
             InitError::IssuesRepoNotFound => Self::new(E_ISSUES_DIR_EXIST, format!("{}", e)),
+            InitError::RevisionNotFound(_) | InitError::CheckedOut(_) => {
+                Self::new(posix_errors::EINVAL, format!("{}", e))
+            }
+        }
+    }
+}
+impl From<HistoryError> for PosixError {
+    #[inline]
+    fn from(e: HistoryError) -> Self {
+        match e {
+            HistoryError::Io(err) => err.into(),
+            HistoryError::Git(msg) => Self::new(posix_errors::EIO, msg),
+            HistoryError::ParseError(err) => Self::new(posix_errors::EINVAL, format!("{}", err)),
         }
     }
 }
@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+
+use getset::Getters;
+use time::OffsetDateTime;
+
+use crate::errors::HistoryError;
+use crate::history::{FileChange, HistoryCommit, State};
+use crate::{DataSource, Id};
+
+/// What happened to an issue
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EventKind {
+    /// Issue was created
+    Created,
+    /// Tag was added
+    TagAdded(String),
+    /// Tag was removed
+    TagRemoved(String),
+    /// Milestone was set or removed (`None`)
+    MilestoneSet(Option<String>),
+    /// Description was edited
+    DescriptionEdited,
+    /// Comment was added
+    CommentAdded,
+    /// Issue was closed
+    Closed,
+}
+
+impl std::fmt::Display for EventKind {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Created => write!(f, "created"),
+            Self::TagAdded(tag) => write!(f, "added tag {}", tag),
+            Self::TagRemoved(tag) => write!(f, "removed tag {}", tag),
+            Self::MilestoneSet(Some(milestone)) => write!(f, "set milestone {}", milestone),
+            Self::MilestoneSet(None) => write!(f, "removed milestone"),
+            Self::DescriptionEdited => write!(f, "edited description"),
+            Self::CommentAdded => write!(f, "added comment"),
+            Self::Closed => write!(f, "closed"),
+        }
+    }
+}
+
+/// A single change to an issue
+#[derive(Clone, Debug, Getters, PartialEq, Eq)]
+#[getset(get = "pub")]
+pub struct Event {
+    /// The changed issue
+    id: Id,
+    /// Title of the issue at the end of the history
+    title: String,
+    /// Commit sha
+    sha: String,
+    /// Author name
+    author_name: String,
+    /// Author email
+    author_email: String,
+    /// Author date
+    date: OffsetDateTime,
+    /// What happened
+    kind: EventKind,
+}
+
+impl DataSource {
+    /// Return the changes to all issues or a single one, oldest first
+    ///
+    /// # Errors
+    ///
+    /// Will throw an error if executing git(1) fails
+    #[inline]
+    pub fn events(&self, id: Option<&Id>) -> Result<Vec<Event>, HistoryError> {
+        Ok(events(&self.history(id, &[])?))
+    }
+}
+
+/// Derive the events from the history of the issue files
+pub(crate) fn events(commits: &[HistoryCommit]) -> Vec<Event> {
+    let mut titles: HashMap<&Id, String> = HashMap::new();
+    for change in commits.iter().flat_map(HistoryCommit::changes) {
+        if let ("description", Some(text)) = (change.file().as_str(), change.new()) {
+            let title = text.lines().next().unwrap_or_default().to_owned();
+            titles.insert(change.id(), title);
+        }
+    }
+
+    let mut result = vec![];
+    for commit in commits {
+        for change in commit.changes() {
+            let title = titles.get(change.id()).cloned().unwrap_or_default();
+            for kind in kinds(change) {
+                result.push(Event {
+                    id: change.id().clone(),
+                    title: title.clone(),
+                    sha: commit.sha().clone(),
+                    author_name: commit.author_name().clone(),
+                    author_email: commit.author_email().clone(),
+                    date: *commit.date(),
+                    kind,
+                });
+            }
+        }
+    }
+    result
+}
+
+/// Return the events described by a single file change
+fn kinds(change: &FileChange) -> Vec<EventKind> {
+    let file = change.file().as_str();
+    match (file, change.old(), change.new()) {
+        ("description", None, Some(_)) => vec![EventKind::Created],
+        ("description", Some(old), Some(new)) if old != new => vec![EventKind::DescriptionEdited],
+        ("milestone", _, new) if change.old() != new => {
+            vec![EventKind::MilestoneSet(new.clone())]
+        }
+        ("tags", old, new) => {
+            let old_tags: Vec<&str> = old.as_deref().unwrap_or_default().lines().collect();
+            let new_tags: Vec<&str> = new.as_deref().unwrap_or_default().lines().collect();
+            let state = |tags: &Option<String>| tags.as_deref().map(State::from_tags);
+            let closed = state(old) == Some(State::Open) && state(new) == Some(State::Closed);
+            // The `open` & `closed` tags are reported as state changes, empty lines are ignored
+            let implied = |tag: &&str| tag.is_empty() || *tag == "open" || *tag == "closed";
+            let mut result: Vec<EventKind> = new_tags
+                .iter()
+                .filter(|t| !old_tags.contains(t) && !implied(t))
+                .map(|t| EventKind::TagAdded((*t).to_owned()))
+                .collect();
+            result.extend(
+                old_tags
+                    .iter()
+                    .filter(|t| !new_tags.contains(t) && !implied(t))
+                    .map(|t| EventKind::TagRemoved((*t).to_owned())),
+            );
+            if closed {
+                result.push(EventKind::Closed);
+            }
+            result
+        }
+        (_, None, Some(_)) if file.starts_with("comments/") => vec![EventKind::CommentAdded],
+        _ => vec![],
+    }
+}
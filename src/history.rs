@@ -0,0 +1,449 @@
+use std::collections::HashMap;
+
+use getset::Getters;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::errors::HistoryError;
+use crate::source::Property;
+use crate::{DataSource, Id};
+
+/// A change to a single file of an issue
+#[derive(Clone, Debug, Getters, PartialEq, Eq)]
+#[getset(get = "pub")]
+pub struct FileChange {
+    /// The changed issue
+    id: Id,
+    /// File path relative to the issue directory, e.g. `tags` or `comments/<id>`
+    file: String,
+    /// Content before the change, `None` if the file was added
+    old: Option<String>,
+    /// Content after the change, `None` if the file was removed
+    new: Option<String>,
+}
+
+/// A commit changing issue files
+#[derive(Clone, Debug, Getters, PartialEq, Eq)]
+#[getset(get = "pub")]
+pub struct HistoryCommit {
+    /// Commit sha
+    sha: String,
+    /// Author name
+    author_name: String,
+    /// Author email
+    author_email: String,
+    /// Author date
+    date: OffsetDateTime,
+    /// First line of the commit message
+    subject: String,
+    /// Changed issue files
+    changes: Vec<FileChange>,
+}
+
+/// Whether an issue is open or closed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum State {
+    /// Issue has the `open` tag
+    Open,
+    /// Issue lost the `open` tag or has the `closed` tag
+    Closed,
+}
+
+impl State {
+    /// Derive the state from the content of a `tags` file
+    #[inline]
+    #[must_use]
+    pub fn from_tags(tags: &str) -> Self {
+        let mut lines = tags.lines();
+        if lines.clone().any(|t| t == "closed") || !lines.any(|t| t == "open") {
+            Self::Closed
+        } else {
+            Self::Open
+        }
+    }
+}
+
+/// An issue being opened, closed or reopened
+#[derive(Clone, Debug, Getters, PartialEq, Eq)]
+#[getset(get = "pub")]
+pub struct StateChange {
+    /// The issue
+    id: Id,
+    /// New state
+    state: State,
+    /// Date of the change
+    date: OffsetDateTime,
+}
+
+/// A change to a single property of an issue
+#[derive(Clone, Debug, Getters, PartialEq, Eq)]
+#[getset(get = "pub")]
+pub struct HistoryEntry {
+    /// Commit sha
+    sha: String,
+    /// Author name
+    author_name: String,
+    /// Author email
+    author_email: String,
+    /// Author date
+    date: OffsetDateTime,
+    /// First line of the commit message
+    subject: String,
+    /// The changed property
+    property: Property,
+    /// Value before the change, `None` if it was unset
+    old: Option<String>,
+    /// Value after the change, `None` if it was removed
+    new: Option<String>,
+}
+
+/// A line of a description diff
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DiffLine {
+    /// Line is in both versions
+    Unchanged(String),
+    /// Line was added
+    Added(String),
+    /// Line was removed
+    Removed(String),
+}
+
+impl HistoryEntry {
+    /// Return the tags added by this change
+    #[inline]
+    #[must_use]
+    pub fn added_tags(&self) -> Vec<&str> {
+        tag_diff(self.new.as_deref(), self.old.as_deref())
+    }
+
+    /// Return the tags removed by this change
+    #[inline]
+    #[must_use]
+    pub fn removed_tags(&self) -> Vec<&str> {
+        tag_diff(self.old.as_deref(), self.new.as_deref())
+    }
+
+    /// Return the line diff between the old and the new value
+    #[inline]
+    #[must_use]
+    pub fn diff(&self) -> Vec<DiffLine> {
+        let old: Vec<&str> = self.old.as_deref().unwrap_or_default().lines().collect();
+        let new: Vec<&str> = self.new.as_deref().unwrap_or_default().lines().collect();
+        diff_lines(&old, &new)
+    }
+}
+
+/// Return the tags in `left` which are not in `right`
+fn tag_diff<'a>(left: Option<&'a str>, right: Option<&str>) -> Vec<&'a str> {
+    let right: Vec<&str> = right.unwrap_or_default().lines().collect();
+    left.unwrap_or_default()
+        .lines()
+        .filter(|t| !t.is_empty() && !right.contains(t))
+        .collect()
+}
+
+/// Compute a line diff using the longest common subsequence
+// arithmetic: the indices are bounded by the lengths of `old` & `new`
+#[allow(clippy::arithmetic_side_effects, clippy::indexing_slicing)]
+fn diff_lines(old: &[&str], new: &[&str]) -> Vec<DiffLine> {
+    // lcs[i][j] is the length of the longest common subsequence of old[i..] & new[j..]
+    let mut lcs = vec![vec![0_usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut result = vec![];
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            result.push(DiffLine::Unchanged(old[i].to_owned()));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            result.push(DiffLine::Removed(old[i].to_owned()));
+            i += 1;
+        } else {
+            result.push(DiffLine::Added(new[j].to_owned()));
+            j += 1;
+        }
+    }
+    result
+}
+
+/// Map a file path relative to the issue directory to the property stored in it
+fn property(file: &str) -> Property {
+    match file {
+        "description" => Property::Description,
+        "duedate" => Property::DueDate,
+        "milestone" => Property::Milestone,
+        "tags" => Property::Tags,
+        _ => file.strip_prefix("comments/").map_or_else(
+            || Property::Field(file.to_owned()),
+            |id| Property::Comment(id.to_owned()),
+        ),
+    }
+}
+
+impl DataSource {
+    /// Return the property changes of an issue, oldest first
+    ///
+    /// # Errors
+    ///
+    /// Will throw an error if executing git(1) fails
+    #[inline]
+    pub fn issue_history(&self, id: &Id) -> Result<Vec<HistoryEntry>, HistoryError> {
+        let mut result = vec![];
+        for commit in self.history(Some(id), &[])? {
+            for change in commit.changes {
+                result.push(HistoryEntry {
+                    sha: commit.sha.clone(),
+                    author_name: commit.author_name.clone(),
+                    author_email: commit.author_email.clone(),
+                    date: commit.date,
+                    subject: commit.subject.clone(),
+                    property: property(&change.file),
+                    old: change.old,
+                    new: change.new,
+                });
+            }
+        }
+        Ok(result)
+    }
+
+    /// Return the commits changing issue files, oldest first
+    ///
+    /// The history can be narrowed to a single issue and/or to some files of each issue, e.g.
+    /// `tags`. Merge commits are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Will throw an error if executing git(1) fails
+    #[inline]
+    pub fn history(
+        &self,
+        id: Option<&Id>,
+        files: &[&str],
+    ) -> Result<Vec<HistoryCommit>, HistoryError> {
+        self.log(None, id, files)
+    }
+
+    /// Like [`Self::history`], optionally limited to a revision range
+    fn log(
+        &self,
+        range: Option<&str>,
+        id: Option<&Id>,
+        files: &[&str],
+    ) -> Result<Vec<HistoryCommit>, HistoryError> {
+        // Without a revision only the issues in the working tree have a history
+        let issues = self.issues_dir.join("issues");
+        if !self.issues_dir.exists() || !issues.exists() && self.read_rev().is_none() {
+            return Ok(vec![]);
+        }
+        // The paths are relative to the `.issues` directory, which exists even if the issues
+        // are read from a revision
+        let dir = id.map_or_else(
+            || "issues/*/*".to_owned(),
+            |i| format!("issues/{}/{}", &i.id()[..2], &i.id()[2..]),
+        );
+        let pathspecs: Vec<String> = match (id, files) {
+            (None, []) => vec!["issues".to_owned()],
+            (Some(_), []) => vec![dir],
+            (_, _) => files.iter().map(|f| format!("{}/{}", dir, f)).collect(),
+        };
+        let out = self
+            .repo
+            .git()
+            .current_dir(&self.issues_dir)
+            .args([
+                "log",
+                "--reverse",
+                "--raw",
+                "--no-abbrev",
+                "--no-renames",
+                "--relative",
+                "--format=commit %H%x09%aI%x09%aN%x09%aE%x09%s",
+            ])
+            .args(range.or_else(|| self.read_rev()))
+            .arg("--")
+            .args(pathspecs)
+            .output()?;
+        if !out.status.success() {
+            return Err(HistoryError::Git(
+                String::from_utf8_lossy(&out.stderr).to_string(),
+            ));
+        }
+
+        let mut commits = vec![];
+        let mut raw_changes: Vec<Vec<(Id, String, String, String)>> = vec![];
+        for line in String::from_utf8_lossy(&out.stdout).lines() {
+            if let Some(header) = line.strip_prefix("commit ") {
+                let mut split = header.splitn(5, '\t');
+                let mut next = || split.next().unwrap_or_default().to_owned();
+                let (sha, date, author_name, author_email, subject) =
+                    (next(), next(), next(), next(), next());
+                commits.push(HistoryCommit {
+                    sha,
+                    author_name,
+                    author_email,
+                    date: OffsetDateTime::parse(&date, &Rfc3339)?,
+                    subject,
+                    changes: vec![],
+                });
+                raw_changes.push(vec![]);
+            } else if let Some(raw) = line.strip_prefix(':') {
+                // :100644 100644 <old sha> <new sha> M\t<path>
+                let (meta, path) = raw.split_once('\t').unwrap_or((raw, ""));
+                let path = path.strip_prefix("issues/").unwrap_or(path);
+                let fields: Vec<&str> = meta.split(' ').collect();
+                let mut components = path.splitn(3, '/');
+                if let (Some(prefix), Some(rest), Some(file), Some(old), Some(new), Some(last)) = (
+                    components.next(),
+                    components.next(),
+                    components.next(),
+                    fields.get(2),
+                    fields.get(3),
+                    raw_changes.last_mut(),
+                ) {
+                    let id = Id::new(format!("{}{}", prefix, rest));
+                    last.push((id, file.to_owned(), (*old).to_owned(), (*new).to_owned()));
+                }
+            }
+        }
+
+        let blobs = self
+            .objects()
+            .blobs(raw_changes.iter().flatten().flat_map(|(_, _, o, n)| [o, n]))?;
+        let content = |sha: &String| blobs.get(sha).cloned();
+        for (commit, changes) in commits.iter_mut().zip(raw_changes) {
+            commit.changes = changes
+                .into_iter()
+                .map(|(id, file, old, new)| FileChange {
+                    id,
+                    file,
+                    old: content(&old),
+                    new: content(&new),
+                })
+                .collect();
+        }
+        Ok(commits)
+    }
+
+    /// Return the issues closed by commits in a revision range like `v1.0..v1.1`, oldest first
+    ///
+    /// Closing commits are found by their `DONE(<id>)` or `gi: Closed <ids>` subject, ids which
+    /// do not match exactly one issue are skipped. Issues closed without such a commit, e.g. by
+    /// a fast-forward merge, are found by the change of their `tags` file.
+    ///
+    /// # Errors
+    ///
+    /// Will throw an error if executing git(1) fails
+    #[inline]
+    pub fn closed_in_range(&self, range: &str) -> Result<Vec<Id>, HistoryError> {
+        let out = self
+            .repo
+            .git()
+            .args(["log", "--reverse", "--format=%s", range, "--"])
+            .output()?;
+        if !out.status.success() {
+            return Err(HistoryError::Git(
+                String::from_utf8_lossy(&out.stderr).to_string(),
+            ));
+        }
+        let mut result: Vec<Id> = vec![];
+        for subject in String::from_utf8_lossy(&out.stdout).lines() {
+            for short_id in closed_ids(subject) {
+                match self.find_issue(short_id) {
+                    Ok(id) if !result.contains(&id) => result.push(id),
+                    Ok(_) => {}
+                    Err(e) => log::warn!("Skipping {}: {}", short_id, e),
+                }
+            }
+        }
+        for commit in self.log(Some(range), None, &["tags"])? {
+            for change in commit.changes {
+                let closed = |tags: &Option<String>| {
+                    tags.as_deref()
+                        .is_some_and(|t| State::from_tags(t) == State::Closed)
+                };
+                if !closed(&change.old) && closed(&change.new) && !result.contains(&change.id) {
+                    result.push(change.id);
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// Return when issues were opened, closed or reopened, oldest first
+    ///
+    /// The state is derived from the `tags` file: an issue is open while it has the `open` tag
+    /// and no `closed` tag.
+    ///
+    /// # Errors
+    ///
+    /// Will throw an error if executing git(1) fails
+    #[inline]
+    pub fn state_changes(&self) -> Result<Vec<StateChange>, HistoryError> {
+        let commits = self.history(None, &["tags"])?;
+        Ok(state_changes(&commits))
+    }
+
+    /// Return the date an issue was closed or `None` if it is open
+    ///
+    /// # Errors
+    ///
+    /// Will throw an error if executing git(1) fails
+    #[inline]
+    pub fn closing_date(&self, id: &Id) -> Result<Option<OffsetDateTime>, HistoryError> {
+        let commits = self.history(Some(id), &["tags"])?;
+        Ok(state_changes(&commits)
+            .last()
+            .filter(|c| c.state == State::Closed)
+            .map(|c| c.date))
+    }
+}
+
+/// Return the issue ids mentioned in a commit subject written by git-issue-close(1)
+///
+/// Recognizes `DONE(<id>): <title>` and `gi: Closed <id>, <id>…`.
+fn closed_ids(subject: &str) -> Vec<&str> {
+    let ids: Vec<&str> = if let Some(rest) = subject.strip_prefix("DONE(") {
+        rest.split_once("):")
+            .map(|(id, _)| vec![id.trim()])
+            .unwrap_or_default()
+    } else {
+        subject
+            .strip_prefix("gi: Closed ")
+            .map(|ids| ids.split(',').map(str::trim).collect())
+            .unwrap_or_default()
+    };
+    ids.into_iter()
+        .filter(|id| id.len() >= 4 && id.chars().all(|c| c.is_ascii_hexdigit()))
+        .collect()
+}
+
+/// Derive the state changes from the history of the `tags` files
+pub(crate) fn state_changes(commits: &[HistoryCommit]) -> Vec<StateChange> {
+    let mut states: HashMap<&Id, State> = HashMap::new();
+    let mut result = vec![];
+    for commit in commits {
+        for change in commit.changes.iter().filter(|c| c.file == "tags") {
+            let new_state = match &change.new {
+                Some(tags) => State::from_tags(tags),
+                None => continue,
+            };
+            if states.insert(&change.id, new_state) != Some(new_state) {
+                result.push(StateChange {
+                    id: change.id.clone(),
+                    state: new_state,
+                    date: commit.date,
+                });
+            }
+        }
+    }
+    result
+}
@@ -6,7 +6,7 @@ use std::{
 use getset::Getters;
 
 /// Issue id
-#[derive(Clone, Getters, PartialEq, Eq)]
+#[derive(Clone, Getters, Hash, PartialEq, Eq)]
 pub struct Id {
     /// The id itself
     #[getset(get = "pub")]
@@ -38,6 +38,13 @@ impl Id {
             .join(&self.id()[2..])
     }
 
+    /// Return `true` if the id is a full SHA-1 or SHA-256 commit id
+    #[inline]
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        matches!(self.id.len(), 40 | 64) && self.id.chars().all(|c| c.is_ascii_hexdigit())
+    }
+
     /// Returns id shortened to 8 chars
     #[inline]
     #[must_use]
@@ -50,8 +57,8 @@ impl From<&PathBuf> for Id {
     #[inline]
     fn from(path: &PathBuf) -> Self {
         let parent = path.parent().expect("parent dir");
-        let prefix = parent.file_name().expect("File name").to_str().expect("");
-        let file_name = path.file_name().expect("File name").to_str().expect("");
+        let prefix = parent.file_name().expect("File name").to_string_lossy();
+        let file_name = path.file_name().expect("File name").to_string_lossy();
 
         Self {
             id: format!("{}{}", prefix, file_name),
@@ -1,10 +1,18 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
 use getset::Getters;
 use time::format_description::well_known::Rfc3339;
 use time::OffsetDateTime;
 
-use crate::caching::{Cache, CacheError};
+use crate::caching::{Cache, CacheError, Metadata};
+use crate::color;
+use crate::date::{self, DateFormat};
+use crate::errors::HistoryError;
+use crate::history::HistoryEntry;
 use crate::id::{CommentId, Id};
-use crate::source::{DataSource, Property};
+use crate::parallel::par_map;
+use crate::source::{CreationInfo, DataSource, Property};
 
 /// Vector of Strings containing tags
 pub type Tags = Vec<String>;
@@ -68,36 +76,252 @@ impl Comment {
 
 pub type Cdate = OffsetDateTime;
 pub type Ddate = OffsetDateTime;
+pub type Mdate = OffsetDateTime;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Truncate {
+    No,
+    Left,
+    Middle,
+    Right,
+}
+
+/// Width modifier applied to the next placeholder
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Width {
+    align: Align,
+    columns: usize,
+    truncate: Truncate,
+}
+
+impl Width {
+    /// Pad or truncate the text to the requested width
+    // arithmetic: all calculations are guarded by `len < self.columns` or `len > self.columns`
+    #[allow(clippy::arithmetic_side_effects)]
+    fn apply(&self, text: &str) -> String {
+        let len = text.chars().count();
+        if len > self.columns && self.truncate != Truncate::No {
+            if self.columns <= 2 {
+                return text.chars().take(self.columns).collect();
+            }
+            let keep = self.columns - 2;
+            return match self.truncate {
+                Truncate::No => unreachable!("checked above"),
+                Truncate::Right => format!("{}..", text.chars().take(keep).collect::<String>()),
+                Truncate::Left => {
+                    format!("..{}", text.chars().skip(len - keep).collect::<String>())
+                }
+                Truncate::Middle => {
+                    let head = keep / 2;
+                    let tail = keep - head;
+                    format!(
+                        "{}..{}",
+                        text.chars().take(head).collect::<String>(),
+                        text.chars().skip(len - tail).collect::<String>()
+                    )
+                }
+            };
+        }
+        if len >= self.columns {
+            return text.to_owned();
+        }
+        let padding = self.columns - len;
+        match self.align {
+            Align::Left => format!("{}{}", text, " ".repeat(padding)),
+            Align::Right => format!("{}{}", " ".repeat(padding), text),
+            Align::Center => {
+                let left = padding / 2;
+                format!("{}{}{}", " ".repeat(left), text, " ".repeat(padding - left))
+            }
+        }
+    }
+}
 
 #[derive(Clone, Debug)]
 enum PlaceHolders {
+    AuthorEmail,
+    AutoColor,
+    AuthorName,
+    Body,
+    CommentCount,
+    Color(String),
     CreationDate,
+    CreationDateRelative,
     DueDate,
+    DueDateRelative,
     Description,
     Id,
     Milestone,
+    ModificationDate,
+    ModificationDateRelative,
     Tags,
     ShortId,
     Text(String),
+    Width(Width),
 }
 
 /// Format string pattern
+///
+/// Supported placeholders:
+///
+/// - `%i` short id, `%I` full id
+/// - `%D` title, `%B` full description
+/// - `%M` milestone, `%T` tags
+/// - `%c` creation date, `%m` last modification date, `%d` due date, rendered with the
+///   [`DateFormat`] set by [`FormatString::with_date_format`]
+/// - `%cr`, `%mr` relative creation & modification date e.g. `3 days ago`
+/// - `%dr` relative due date e.g. `due in 2 days` or `overdue by 5 days`
+/// - `%an` author name, `%ae` author email of the issue creating commit
+/// - `%N` number of comments
+/// - `%n` new line, `%%` a literal `%`
+///
+/// The width of the next placeholder can be set with `%<(N)` (left aligned), `%>(N)` (right
+/// aligned) or `%><(N)` (centered). Appending `,trunc`, `,ltrunc` or `,mtrunc` to the width
+/// truncates longer values on the right, left or in the middle, like git-log(1) does.
+///
+/// Colours are set with `%C(…)` using git-config(1) colour names, e.g. `%C(bold red)`, and
+/// `%C(reset)`. `%C(auto)` colours the following tags, overdue due dates and closed issues
+/// until the next `%C(…)`. Colours are only emitted if enabled with
+/// [`FormatString::with_color`].
 #[derive(Clone, Debug)]
-pub struct FormatString(Vec<PlaceHolders>);
+pub struct FormatString {
+    placeholders: Vec<PlaceHolders>,
+    color: bool,
+    date: DateFormat,
+}
 
 impl FormatString {
+    /// Set the rendering of `%c`, `%d` & `%m`
+    #[inline]
+    #[must_use]
+    pub fn with_date_format(mut self, date: DateFormat) -> Self {
+        self.date = date;
+        self
+    }
+
+    /// Return `true` if rendering needs the creation date or author of an issue
+    #[inline]
+    #[must_use]
+    pub fn needs_creation(&self) -> bool {
+        self.placeholders.iter().any(|p| {
+            matches!(
+                p,
+                PlaceHolders::AuthorEmail
+                    | PlaceHolders::AuthorName
+                    | PlaceHolders::CreationDate
+                    | PlaceHolders::CreationDateRelative
+            )
+        })
+    }
+
+    /// Enable or disable colours
+    #[inline]
+    #[must_use]
+    pub const fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Return the colour `%C(auto)` uses for a placeholder
+    fn auto_color(ph: &PlaceHolders, issue: &mut Issue<'_>) -> Option<&'static str> {
+        let closed = issue.cache_tags().map(|i| i.is_closed()).unwrap_or(false);
+        match ph {
+            PlaceHolders::Tags => Some(color::CYAN),
+            PlaceHolders::DueDate | PlaceHolders::DueDateRelative => {
+                match issue.inner_ddate.flatten() {
+                    Some(ddate) if !closed && ddate < OffsetDateTime::now_utc() => {
+                        Some(color::BOLD_RED)
+                    }
+                    _ => None,
+                }
+            }
+            PlaceHolders::Id | PlaceHolders::ShortId | PlaceHolders::Description if closed => {
+                Some(color::DIM)
+            }
+            _ => None,
+        }
+    }
+
     /// Return issue formatted as string
     #[inline]
     pub fn format(&self, issue: &mut Issue<'_>) -> String {
         let mut result = String::new();
-        for ph in &self.0 {
+        let mut width: Option<Width> = None;
+        let mut auto = false;
+        for ph in &self.placeholders {
             let text = match ph {
+                PlaceHolders::Text(t) => {
+                    result.push_str(t);
+                    continue;
+                }
+                PlaceHolders::Width(w) => {
+                    width = Some(*w);
+                    continue;
+                }
+                PlaceHolders::AutoColor => {
+                    auto = true;
+                    continue;
+                }
+                PlaceHolders::Color(c) => {
+                    auto = false;
+                    if self.color {
+                        result.push_str(c);
+                    }
+                    continue;
+                }
+                PlaceHolders::AuthorEmail => {
+                    if let Err(e) = issue.cache_author() {
+                        log::error!("author for id({}) {}", e, issue.id().short_id());
+                        String::default()
+                    } else {
+                        issue.author_email().clone()
+                    }
+                }
+                PlaceHolders::AuthorName => {
+                    if let Err(e) = issue.cache_author() {
+                        log::error!("author for id({}) {}", e, issue.id().short_id());
+                        String::default()
+                    } else {
+                        issue.author_name().clone()
+                    }
+                }
+                PlaceHolders::Body => {
+                    if let Err(e) = issue.cache_desc() {
+                        log::error!("desc for id({}) {}", e, issue.id().short_id());
+                        String::default()
+                    } else {
+                        issue.desc().clone()
+                    }
+                }
+                PlaceHolders::CommentCount => {
+                    if let Err(e) = issue.cache_comment_count() {
+                        log::error!("comments for id({}) {}", e, issue.id().short_id());
+                        String::default()
+                    } else {
+                        issue.comment_count().to_string()
+                    }
+                }
                 PlaceHolders::CreationDate => {
                     if let Err(e) = issue.cache_cdate() {
                         log::error!("creation date for id({}) {}", e, issue.id().short_id());
                         String::default()
                     } else {
-                        issue.cdate().to_string()
+                        self.date.format(issue.cdate())
+                    }
+                }
+                PlaceHolders::CreationDateRelative => {
+                    if let Err(e) = issue.cache_cdate() {
+                        log::error!("creation date for id({}) {}", e, issue.id().short_id());
+                        String::default()
+                    } else {
+                        date::relative(issue.cdate(), &OffsetDateTime::now_utc())
                     }
                 }
                 PlaceHolders::DueDate => {
@@ -105,11 +329,25 @@ impl FormatString {
                         log::error!("due date for id({}) {}", e, issue.id().short_id());
                         String::default()
                     } else {
-                        issue.ddate().map(|v| v.to_string()).unwrap_or_default()
+                        issue
+                            .ddate()
+                            .map(|v| self.date.format(&v))
+                            .unwrap_or_default()
+                    }
+                }
+                PlaceHolders::DueDateRelative => {
+                    if let Err(e) = issue.cache_ddate() {
+                        log::error!("due date for id({}) {}", e, issue.id().short_id());
+                        String::default()
+                    } else {
+                        issue
+                            .ddate()
+                            .map(|v| date::due_relative(&v, &OffsetDateTime::now_utc()))
+                            .unwrap_or_default()
                     }
                 }
                 PlaceHolders::Description => {
-                    if let Err(e) = issue.cache_desc() {
+                    if let Err(e) = issue.cache_title() {
                         log::error!("desc for id({}) {}", e, issue.id().short_id());
                         String::default()
                     } else {
@@ -129,6 +367,22 @@ impl FormatString {
                             .map_or_else(String::default, ToString::to_string)
                     }
                 }
+                PlaceHolders::ModificationDate => {
+                    if let Err(e) = issue.cache_mdate() {
+                        log::error!("modification date for id({}) {}", e, issue.id().short_id());
+                        String::default()
+                    } else {
+                        self.date.format(issue.mdate())
+                    }
+                }
+                PlaceHolders::ModificationDateRelative => {
+                    if let Err(e) = issue.cache_mdate() {
+                        log::error!("modification date for id({}) {}", e, issue.id().short_id());
+                        String::default()
+                    } else {
+                        date::relative(issue.mdate(), &OffsetDateTime::now_utc())
+                    }
+                }
                 PlaceHolders::Tags => {
                     if let Err(e) = issue.cache_tags() {
                         log::error!("tags for id({}) {}", e, issue.id().short_id());
@@ -137,9 +391,20 @@ impl FormatString {
                         issue.tags().join(" ")
                     }
                 }
-                PlaceHolders::Text(t) => t.to_string(),
             };
-            result.push_str(&text);
+            let text = match width.take() {
+                Some(w) => w.apply(&text),
+                None => text,
+            };
+            let auto_color = if auto && self.color {
+                Self::auto_color(ph, issue)
+            } else {
+                None
+            };
+            match auto_color {
+                Some(c) => result.push_str(&color::paint(c, &text)),
+                None => result.push_str(&text),
+            }
         }
         result
     }
@@ -159,55 +424,79 @@ impl FormatString {
             "short" => "ID: %i%nDate: %c%nDue Date: %d%nTags: %T%nDescription: %D",
             f => f,
         };
-        let mut chars = format_string.chars();
-        while let Some(c) = chars.next() {
+        // Columns are counted from 1, like editors do
+        let mut chars = format_string.chars().zip(1_usize..).peekable();
+        while let Some((c, column)) = chars.next() {
             if c == '%' {
                 match chars.next() {
-                    Some('%') => {
+                    Some(('%', _)) => {
                         cur.push('%');
                     }
-                    Some('n') => {
+                    Some(('n', _)) => {
                         cur.push('\n');
                     }
-                    Some(n) => {
+                    Some((n, _)) => {
                         if !cur.is_empty() {
                             result.push(PlaceHolders::Text(cur.clone()));
+                            cur = String::new();
                         }
-                        match n {
-                            'i' => {
-                                result.push(PlaceHolders::ShortId);
-                            }
-                            'I' => {
-                                result.push(PlaceHolders::Id);
-                            }
-                            'D' => {
-                                result.push(PlaceHolders::Description);
-                            }
-                            'M' => {
-                                result.push(PlaceHolders::Milestone);
-                            }
-                            'c' => {
-                                result.push(PlaceHolders::CreationDate);
-                            }
-                            'd' => {
-                                result.push(PlaceHolders::DueDate);
-                            }
-                            'T' => {
-                                result.push(PlaceHolders::Tags);
+                        let placeholder = match n {
+                            'i' => PlaceHolders::ShortId,
+                            'I' => PlaceHolders::Id,
+                            'B' => PlaceHolders::Body,
+                            'D' => PlaceHolders::Description,
+                            'M' => PlaceHolders::Milestone,
+                            'N' => PlaceHolders::CommentCount,
+                            'c' => Self::relative(
+                                &mut chars,
+                                PlaceHolders::CreationDate,
+                                PlaceHolders::CreationDateRelative,
+                            ),
+                            'd' => Self::relative(
+                                &mut chars,
+                                PlaceHolders::DueDate,
+                                PlaceHolders::DueDateRelative,
+                            ),
+                            'm' => Self::relative(
+                                &mut chars,
+                                PlaceHolders::ModificationDate,
+                                PlaceHolders::ModificationDateRelative,
+                            ),
+                            'T' => PlaceHolders::Tags,
+                            'C' => Self::parse_color(&mut chars, column)?,
+                            'a' => match chars.next() {
+                                Some(('n', _)) => PlaceHolders::AuthorName,
+                                Some(('e', _)) => PlaceHolders::AuthorEmail,
+                                Some((x, _)) => {
+                                    return Err(format!(
+                                        "Unexpected formatstring place holder '%a{}' at column {}",
+                                        x, column
+                                    ));
+                                }
+                                None => {
+                                    return Err(format!(
+                                        "Premature end of string at column {}. Expected '%an' or '%ae'",
+                                        column
+                                    ));
+                                }
+                            },
+                            '<' | '>' => {
+                                PlaceHolders::Width(Self::parse_width(n, &mut chars, column)?)
                             }
                             _ => {
                                 return Err(format!(
-                                    "Unexpected formatstring place holder '{}{}'",
-                                    c, n
+                                    "Unexpected formatstring place holder '{}{}' at column {}",
+                                    c, n, column
                                 ));
                             }
-                        }
-                        if !cur.is_empty() {
-                            cur = String::new();
-                        }
+                        };
+                        result.push(placeholder);
                     }
                     None => {
-                        return Err("Premature end of string. Expected placeholder".to_owned());
+                        return Err(format!(
+                            "Premature end of string at column {}. Expected placeholder",
+                            column
+                        ));
                     }
                 }
             } else {
@@ -217,7 +506,94 @@ impl FormatString {
         if !cur.is_empty() {
             result.push(PlaceHolders::Text(cur));
         }
-        Ok(Self(result))
+        Ok(Self {
+            placeholders: result,
+            color: false,
+            date: DateFormat::default(),
+        })
+    }
+
+    /// Return `relative` if the next character is an `r`, like in `%cr`
+    fn relative(
+        chars: &mut std::iter::Peekable<impl Iterator<Item = (char, usize)>>,
+        absolute: PlaceHolders,
+        relative: PlaceHolders,
+    ) -> PlaceHolders {
+        if chars.next_if(|(c, _)| *c == 'r').is_some() {
+            relative
+        } else {
+            absolute
+        }
+    }
+
+    /// Parse the `(…)` part of `%C(…)`
+    fn parse_color(
+        chars: &mut std::iter::Peekable<impl Iterator<Item = (char, usize)>>,
+        column: usize,
+    ) -> Result<PlaceHolders, String> {
+        let invalid = || format!("Invalid color at column {}", column);
+        if chars.next().map(|(c, _)| c) != Some('(') {
+            return Err(invalid());
+        }
+        let mut spec = String::new();
+        loop {
+            match chars.next() {
+                Some((')', _)) => break,
+                Some((c, _)) => spec.push(c),
+                None => return Err(invalid()),
+            }
+        }
+        if spec.trim() == "auto" {
+            return Ok(PlaceHolders::AutoColor);
+        }
+        color::parse(&spec)
+            .map(PlaceHolders::Color)
+            .ok_or_else(invalid)
+    }
+
+    /// Parse the `(N[,trunc])` part of `%<(…)`, `%>(…)` & `%><(…)`
+    fn parse_width(
+        first: char,
+        chars: &mut std::iter::Peekable<impl Iterator<Item = (char, usize)>>,
+        column: usize,
+    ) -> Result<Width, String> {
+        let invalid = || format!("Invalid width modifier at column {}", column);
+        let align = match (first, chars.peek()) {
+            ('>', Some(('<', _))) => {
+                chars.next();
+                Align::Center
+            }
+            ('>', _) => Align::Right,
+            _ => Align::Left,
+        };
+        if chars.next().map(|(c, _)| c) != Some('(') {
+            return Err(invalid());
+        }
+        let mut spec = String::new();
+        loop {
+            match chars.next() {
+                Some((')', _)) => break,
+                Some((c, _)) => spec.push(c),
+                None => return Err(invalid()),
+            }
+        }
+        let mut parts = spec.splitn(2, ',');
+        let columns = parts
+            .next()
+            .and_then(|n| n.trim().parse::<usize>().ok())
+            .ok_or_else(invalid)?;
+        let truncate = match parts.next().map(str::trim) {
+            None => Truncate::No,
+            Some("trunc") => Truncate::Right,
+            Some("ltrunc") => Truncate::Left,
+            Some("mtrunc") => Truncate::Middle,
+            Some(_) => return Err(invalid()),
+        };
+        Ok(Width {
+            align,
+            columns,
+            truncate,
+        })
     }
 }
 
@@ -225,12 +601,17 @@ impl FormatString {
 #[derive(Debug)]
 pub struct Issue<'src> {
     id: Id,
+    inner_author: Cache<(String, String)>,
     inner_cdate: Cache<Cdate>,
+    inner_comment_count: Cache<usize>,
     inner_comments: Cache<Vec<Comment>>,
     inner_ddate: Cache<Option<Ddate>>,
     inner_desc: Cache<String>,
+    inner_fields: HashMap<String, Option<String>>,
+    inner_mdate: Cache<Mdate>,
     inner_milestone: Cache<Option<String>>,
     inner_tags: Cache<Tags>,
+    inner_title: Cache<String>,
     src: &'src DataSource,
 }
 
@@ -238,15 +619,20 @@ impl<'src> Issue<'src> {
     /// Create new Issue from `Id`
     #[inline]
     #[must_use]
-    pub const fn new(src: &'src DataSource, id: Id) -> Issue<'src> {
+    pub fn new(src: &'src DataSource, id: Id) -> Issue<'src> {
         Issue {
             id,
+            inner_author: None,
             inner_cdate: None,
+            inner_comment_count: None,
             inner_comments: None,
             inner_ddate: None,
             inner_desc: None,
+            inner_fields: HashMap::new(),
+            inner_mdate: None,
             inner_milestone: None,
             inner_tags: None,
+            inner_title: None,
             src,
         }
     }
@@ -258,6 +644,23 @@ impl<'src> Issue<'src> {
         &self.id
     }
 
+    /// Return the path of the issue directory
+    #[inline]
+    #[must_use]
+    pub fn path(&self) -> PathBuf {
+        self.id.path(&self.src.issues_dir)
+    }
+
+    /// Return the property changes of the issue, oldest first
+    ///
+    /// # Errors
+    ///
+    /// Will throw an error if executing git(1) fails
+    #[inline]
+    pub fn history(&self) -> Result<Vec<HistoryEntry>, HistoryError> {
+        self.src.issue_history(&self.id)
+    }
+
     /// Return `true` if issue is closed
     #[inline]
     #[must_use]
@@ -265,6 +668,141 @@ impl<'src> Issue<'src> {
         self.tags().contains(&"closed".to_owned())
     }
 
+    /// Cache the author of the issue creating commit
+    ///
+    /// # Errors
+    ///
+    /// Error during caching
+    #[inline]
+    pub fn cache_author(&mut self) -> Result<&mut Self, CacheError> {
+        if self.inner_author.is_none() {
+            let info = self.creation_info()?;
+            self.inner_author = Some((info.author_name().clone(), info.author_email().clone()));
+        }
+        Ok(self)
+    }
+
+    /// Look up the creation date & author of this issue
+    fn creation_info(&self) -> Result<CreationInfo, CacheError> {
+        self.src.creation_info(&self.id)
+    }
+
+    /// Cache the creation date & author of many issues at once
+    ///
+    /// All issues have to belong to the same `DataSource`.
+    ///
+    /// # Errors
+    ///
+    /// Error during caching
+    #[inline]
+    pub fn cache_creation(issues: &mut [Issue<'_>]) -> Result<(), CacheError> {
+        let src = match issues.first() {
+            Some(issue) => issue.src,
+            None => return Ok(()),
+        };
+        let missing = issues
+            .iter()
+            .filter(|i| i.inner_cdate.is_none() || i.inner_author.is_none())
+            .map(Issue::id);
+        let mut infos = src.creation_infos(missing)?;
+        for issue in issues.iter_mut() {
+            if let Some(info) = infos.remove(&issue.id) {
+                issue.inner_cdate = Some(*info.date());
+                issue.inner_author =
+                    Some((info.author_name().clone(), info.author_email().clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Cache tags, milestone, title, creation date & author and comment count of many issues
+    ///
+    /// The metadata of issues whose directory tree did not change since the last call is read
+    /// from the on-disk cache in the git directory, without reading the issue files or running
+    /// git(1) per issue. The metadata of all other issues is read and the cache is updated.
+    ///
+    /// All issues have to belong to the same `DataSource`.
+    ///
+    /// # Errors
+    ///
+    /// Error during caching or writing the cache file
+    #[inline]
+    pub fn cache_metadata(issues: &mut [Issue<'_>]) -> Result<(), CacheError> {
+        let src = match issues.first() {
+            Some(issue) => issue.src,
+            None => return Ok(()),
+        };
+        let trees = src.trees();
+        let mut disk_cache = match (trees.is_empty(), src.disk_cache()) {
+            (false, Some(cache)) => cache,
+            _ => return Ok(()),
+        };
+
+        let total = issues.len();
+        let mut misses: Vec<&mut Issue<'_>> = vec![];
+        for issue in issues.iter_mut() {
+            let cached = trees
+                .get(&issue.id)
+                .and_then(|tree| disk_cache.get(&issue.id, tree));
+            match cached {
+                Some(metadata) => issue.fill(metadata.clone()),
+                None => misses.push(issue),
+            }
+        }
+        log::debug!(
+            "{} of {} issues served from {:?}",
+            total.saturating_sub(misses.len()),
+            total,
+            disk_cache.path()
+        );
+
+        if !misses.is_empty() {
+            for result in par_map(misses, |issue| -> Result<(), CacheError> {
+                issue.cache_tags()?.cache_milestone()?.cache_title()?;
+                issue.cache_comment_count().map(drop)
+            }) {
+                result?;
+            }
+            Issue::cache_creation(issues)?;
+        }
+
+        let entries = issues
+            .iter()
+            .filter_map(|issue| {
+                let tree = trees.get(&issue.id)?;
+                Some((issue.id.clone(), issue.metadata(tree.clone())?))
+            })
+            .collect();
+        disk_cache.update(entries, &trees)?;
+        Ok(())
+    }
+
+    /// Fill the caches from the on-disk cache
+    fn fill(&mut self, metadata: Metadata) {
+        self.inner_cdate.get_or_insert(metadata.cdate);
+        self.inner_author
+            .get_or_insert((metadata.author_name, metadata.author_email));
+        self.inner_comment_count
+            .get_or_insert(metadata.comment_count);
+        self.inner_milestone.get_or_insert(metadata.milestone);
+        self.inner_tags.get_or_insert(metadata.tags);
+        self.inner_title.get_or_insert(metadata.title);
+    }
+
+    /// Return the metadata for the on-disk cache, if all of it is cached
+    fn metadata(&self, tree: String) -> Option<Metadata> {
+        Some(Metadata {
+            tree,
+            cdate: self.inner_cdate?,
+            author_name: self.inner_author.as_ref()?.0.clone(),
+            author_email: self.inner_author.as_ref()?.1.clone(),
+            comment_count: self.inner_comment_count?,
+            milestone: self.inner_milestone.clone()?,
+            title: self.inner_title.clone()?,
+            tags: self.inner_tags.clone()?,
+        })
+    }
+
     /// Cache the creation date data
     ///
     /// # Errors
@@ -273,14 +811,7 @@ impl<'src> Issue<'src> {
     #[inline]
     pub fn cache_cdate(&mut self) -> Result<&mut Self, CacheError> {
         if self.inner_cdate.is_none() {
-            let mut cmd = self.src.repo.git();
-            cmd.args(&["show", "--no-patch", "--format=%aI", self.id().id()]);
-            let out = cmd.output()?;
-
-            let output = String::from_utf8_lossy(&out.stdout);
-            let date_text = output.trim();
-            self.inner_cdate =
-                Some(OffsetDateTime::parse(date_text, &Rfc3339).expect("Valid RFC-3339 date"));
+            self.inner_cdate = Some(*self.creation_info()?.date());
         }
         Ok(self)
     }
@@ -310,6 +841,19 @@ impl<'src> Issue<'src> {
         }
     }
 
+    /// Cache the number of comments
+    ///
+    /// # Errors
+    ///
+    /// Error during caching
+    #[inline]
+    pub fn cache_comment_count(&mut self) -> Result<&mut Self, CacheError> {
+        if self.inner_comment_count.is_none() {
+            self.inner_comment_count = Some(self.src.comment_ids(&self.id).len());
+        }
+        Ok(self)
+    }
+
     /// Cache the due date data
     ///
     /// # Errors
@@ -318,13 +862,10 @@ impl<'src> Issue<'src> {
     #[inline]
     pub fn cache_ddate(&mut self) -> Result<&mut Self, CacheError> {
         if self.inner_ddate.is_none() {
-            self.inner_ddate = Some(
-                if let Ok(date_text) = self.src.read(self.id(), &Property::DueDate) {
-                    Some(OffsetDateTime::parse(&date_text, &Rfc3339).expect("Valid RFC-3339 date"))
-                } else {
-                    None
-                },
-            );
+            self.inner_ddate = Some(match self.src.read(self.id(), &Property::DueDate) {
+                Ok(date_text) => Some(OffsetDateTime::parse(&date_text, &Rfc3339)?),
+                Err(_) => None,
+            });
         }
         Ok(self)
     }
@@ -343,6 +884,55 @@ impl<'src> Issue<'src> {
         Ok(self)
     }
 
+    /// Cache the title, the first line of the description
+    ///
+    /// # Errors
+    ///
+    /// Error during caching
+    #[inline]
+    pub fn cache_title(&mut self) -> Result<&mut Self, CacheError> {
+        if self.inner_title.is_none() {
+            self.cache_desc()?;
+            self.inner_title = Some(self.desc().lines().next().unwrap_or("").to_owned());
+        }
+        Ok(self)
+    }
+
+    /// Cache a custom field like `assignee` or `weight`
+    ///
+    /// # Errors
+    ///
+    /// Error during caching
+    #[inline]
+    pub fn cache_field(&mut self, name: &str) -> Result<&mut Self, CacheError> {
+        if !self.inner_fields.contains_key(name) {
+            let value = self.src.field(self.id(), name);
+            self.inner_fields.insert(name.to_owned(), value);
+        }
+        Ok(self)
+    }
+
+    /// Cache the date of the last commit touching the issue
+    ///
+    /// # Errors
+    ///
+    /// Error during caching
+    #[inline]
+    pub fn cache_mdate(&mut self) -> Result<&mut Self, CacheError> {
+        if self.inner_mdate.is_none() {
+            let mut cmd = self.src.repo.git();
+            cmd.args(["log", "-1", "--format=%aI"])
+                .args(self.src.read_rev())
+                .arg("--")
+                .arg(self.id().path(&self.src.issues_dir));
+            let out = cmd.output()?;
+
+            let output = String::from_utf8_lossy(&out.stdout);
+            self.inner_mdate = Some(OffsetDateTime::parse(output.trim(), &Rfc3339)?);
+        }
+        Ok(self)
+    }
+
     /// Cache the milestone data
     ///
     /// # Errors
@@ -376,6 +966,20 @@ impl<'src> Issue<'src> {
         Ok(self)
     }
 
+    /// Return the name of the issue author
+    #[inline]
+    #[must_use]
+    pub fn author_name(&self) -> &'_ String {
+        &self.inner_author.as_ref().expect("Cached author").0
+    }
+
+    /// Return the email of the issue author
+    #[inline]
+    #[must_use]
+    pub fn author_email(&self) -> &'_ String {
+        &self.inner_author.as_ref().expect("Cached author").1
+    }
+
     /// Return the issue creation date
     #[inline]
     #[must_use]
@@ -383,6 +987,16 @@ impl<'src> Issue<'src> {
         self.inner_cdate.as_ref().expect("Cached creation date")
     }
 
+    /// Return the number of issue comments
+    #[inline]
+    #[must_use]
+    pub fn comment_count(&self) -> usize {
+        *self
+            .inner_comment_count
+            .as_ref()
+            .expect("Cached comment count")
+    }
+
     /// Return issue comments
     #[inline]
     #[must_use]
@@ -404,6 +1018,20 @@ impl<'src> Issue<'src> {
         self.inner_desc.as_ref().expect("Cached description")
     }
 
+    /// Return the value of a custom field
+    #[inline]
+    #[must_use]
+    pub fn field(&self, name: &str) -> Option<&String> {
+        self.inner_fields.get(name).expect("Cached field").as_ref()
+    }
+
+    /// Return the date of the last change to the issue
+    #[inline]
+    #[must_use]
+    pub fn mdate(&self) -> &'_ Mdate {
+        self.inner_mdate.as_ref().expect("Cached modification date")
+    }
+
     /// Return the issue milestone
     #[inline]
     #[must_use]
@@ -424,6 +1052,9 @@ impl<'src> Issue<'src> {
     #[inline]
     #[must_use]
     pub fn title(&self) -> String {
-        self.desc().lines().next().unwrap_or("").to_owned()
+        match &self.inner_title {
+            Some(title) => title.clone(),
+            None => self.desc().lines().next().unwrap_or("").to_owned(),
+        }
     }
 }
@@ -6,16 +6,35 @@ use git_wrapper::{CommitError, Repository};
 use posix_errors::PosixError;
 
 mod caching;
+mod change_set;
+mod color;
+mod date;
 mod errors;
+mod event;
+mod history;
 mod id;
 mod issues;
+mod objects;
+mod pager;
+mod parallel;
 mod source;
+mod store;
+mod transaction;
 pub use crate::caching::CacheError;
+pub use crate::change_set::ChangeSet;
+pub use crate::color::ColorChoice;
+pub use crate::date::{approxidate, format_duration, parse_duration, DateFormat};
 pub use crate::errors::*;
+pub use crate::event::{Event, EventKind};
+pub use crate::history::{DiffLine, FileChange, HistoryCommit, HistoryEntry, State, StateChange};
 pub use crate::id::CommentId;
 pub use crate::id::Id;
 pub use crate::issues::{Comment, FormatString, Issue};
-pub use crate::source::{DataSource, WriteResult};
+pub use crate::pager::Output;
+pub use crate::parallel::par_map;
+pub use crate::source::{CreationInfo, DataSource, Property, WriteResult};
+pub use crate::store::{GitObjectStore, IssueStore, MemoryStore, WorkTreeStore};
+pub use crate::transaction::{MergeStrategy, Transaction};
 
 /// `$EDITOR` was quit with error
 pub const E_EDITOR_KILLED: i32 = posix_errors::EINTR; // 4
@@ -571,3 +590,1234 @@ mod milestone {
         }
     }
 }
+
+#[cfg(test)]
+mod format_string {
+    use crate::FormatString;
+
+    #[test]
+    fn unknown_placeholder() {
+        let err = FormatString::try_new("%i %x").unwrap_err();
+        assert!(err.contains("'%x' at column 4"), "{}", err);
+    }
+
+    #[test]
+    fn unknown_author_placeholder() {
+        let err = FormatString::try_new("%ax").unwrap_err();
+        assert!(err.contains("'%ax' at column 1"), "{}", err);
+    }
+
+    #[test]
+    fn invalid_width() {
+        FormatString::try_new("%<(foo)%D").unwrap_err();
+        FormatString::try_new("%<(10,cut)%D").unwrap_err();
+        FormatString::try_new("%<(10%D").unwrap_err();
+    }
+
+    #[test]
+    fn placeholders() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let data = crate::test_source(tmp_dir.path());
+        let id = data
+            .create_issue("Foo Bar\n\nBaz", vec![], Some("v1".to_owned()))
+            .unwrap();
+        let mut issue = data.find(id.id()).unwrap();
+
+        let format = FormatString::try_new("%an <%ae> %N %M%n%B").unwrap();
+        let actual = format.format(&mut issue);
+        assert_eq!(
+            actual,
+            "Max Musterman <max@example.com> 0 v1\nFoo Bar\n\nBaz"
+        );
+    }
+
+    #[test]
+    fn width() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let data = crate::test_source(tmp_dir.path());
+        let id = data
+            .create_issue("Foo Bar Baz", vec![], Some("v1".to_owned()))
+            .unwrap();
+        let mut issue = data.find(id.id()).unwrap();
+
+        let cases = [
+            ("%<(6)%M|", "v1    |"),
+            ("%>(6)%M|", "    v1|"),
+            ("%><(6)%M|", "  v1  |"),
+            ("%<(6)%M %M|", "v1     v1|"),
+            ("%<(7,trunc)%D|", "Foo B..|"),
+            ("%<(7,ltrunc)%D|", "..r Baz|"),
+            ("%<(7,mtrunc)%D|", "Fo..Baz|"),
+            ("%<(4)%D|", "Foo Bar Baz|"),
+        ];
+        for (format, expected) in cases {
+            let actual = FormatString::try_new(format).unwrap().format(&mut issue);
+            assert_eq!(actual, expected, "Format string {}", format);
+        }
+    }
+}
+
+#[cfg(test)]
+mod format_color {
+    use crate::FormatString;
+
+    #[test]
+    fn disabled() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let data = crate::test_source(tmp_dir.path());
+        let id = data.create_issue("Foo", vec![], None).unwrap();
+        let mut issue = data.find(id.id()).unwrap();
+
+        let format = FormatString::try_new("%C(red)%D%C(reset) %C(auto)%T").unwrap();
+        assert_eq!(format.format(&mut issue), "Foo open");
+    }
+
+    #[test]
+    fn explicit() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let data = crate::test_source(tmp_dir.path());
+        let id = data.create_issue("Foo", vec![], None).unwrap();
+        let mut issue = data.find(id.id()).unwrap();
+
+        let format = FormatString::try_new("%C(bold red)%D%C(reset)")
+            .unwrap()
+            .with_color(true);
+        assert_eq!(format.format(&mut issue), "\x1b[1;31mFoo\x1b[m");
+    }
+
+    #[test]
+    fn auto() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let data = crate::test_source(tmp_dir.path());
+        let id = data.create_issue("Foo", vec![], None).unwrap();
+        data.close_issue(&id).unwrap();
+        let mut issue = data.find(id.id()).unwrap();
+
+        let format = FormatString::try_new("%C(auto)%D %T%C(reset) %D")
+            .unwrap()
+            .with_color(true);
+        assert_eq!(
+            format.format(&mut issue),
+            "\x1b[2mFoo\x1b[m \x1b[36mclosed\x1b[m\x1b[m Foo"
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        FormatString::try_new("%C(foo)%D").unwrap_err();
+        FormatString::try_new("%C(red blue green)%D").unwrap_err();
+        FormatString::try_new("%C(red").unwrap_err();
+    }
+}
+
+#[cfg(test)]
+mod date_format {
+    use time::format_description::well_known::Rfc3339;
+    use time::{Duration, OffsetDateTime};
+
+    use crate::DateFormat;
+
+    fn date() -> OffsetDateTime {
+        OffsetDateTime::parse("2022-03-05T09:07:01+01:00", &Rfc3339).unwrap()
+    }
+
+    fn render(mode: &str) -> String {
+        mode.parse::<DateFormat>().unwrap().format(&date())
+    }
+
+    #[test]
+    fn styles() {
+        assert_eq!(render("default"), "Sat Mar 5 09:07:01 2022 +0100");
+        assert_eq!(render("iso"), "2022-03-05 09:07:01 +0100");
+        assert_eq!(render("iso-strict"), "2022-03-05T09:07:01+01:00");
+        assert_eq!(render("rfc"), "Sat, 5 Mar 2022 09:07:01 +0100");
+        assert_eq!(render("short"), "2022-03-05");
+        assert_eq!(render("unix"), "1646467621");
+        assert_eq!(render("format:%d.%m.%y %I%p %j"), "05.03.22 09AM 064");
+    }
+
+    #[test]
+    fn unknown_style() {
+        "foo".parse::<DateFormat>().unwrap_err();
+    }
+
+    #[test]
+    fn relative() {
+        let now = date();
+        let cases = [
+            (Duration::seconds(-30), "30 seconds ago"),
+            (Duration::minutes(-1), "60 seconds ago"),
+            (Duration::minutes(-5), "5 minutes ago"),
+            (Duration::hours(-5), "5 hours ago"),
+            (Duration::days(-3), "3 days ago"),
+            (Duration::days(-21), "3 weeks ago"),
+            (Duration::days(-100), "3 months ago"),
+            (Duration::days(-800), "2 years ago"),
+            (Duration::days(2), "in 2 days"),
+        ];
+        for (offset, expected) in cases {
+            assert_eq!(crate::date::relative(&(now + offset), &now), expected);
+        }
+    }
+
+    #[test]
+    fn duration() {
+        assert_eq!(crate::parse_duration("7d"), Ok(Duration::days(7)));
+        assert_eq!(crate::parse_duration("1w2d"), Ok(Duration::days(9)));
+        assert_eq!(crate::parse_duration("90m"), Ok(Duration::minutes(90)));
+        assert!(crate::parse_duration("7").is_err(), "Missing unit");
+        assert!(crate::parse_duration("d").is_err(), "Missing number");
+        assert!(crate::parse_duration("7y").is_err(), "Unknown unit");
+        assert!(crate::parse_duration("").is_err(), "Empty duration");
+        assert!(
+            crate::parse_duration("99999999999999w").is_err(),
+            "Overflowing duration"
+        );
+    }
+
+    #[test]
+    fn due_relative() {
+        let now = date();
+        assert_eq!(
+            crate::date::due_relative(&(now + Duration::days(2)), &now),
+            "due in 2 days"
+        );
+        assert_eq!(
+            crate::date::due_relative(&(now - Duration::days(5)), &now),
+            "overdue by 5 days"
+        );
+    }
+}
+
+#[cfg(test)]
+mod history_state {
+    use crate::{DataSource, State};
+
+    #[test]
+    fn closing_date() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let tmp = tmp_dir.path();
+        git_wrapper::setup_test_author();
+        crate::create(tmp, false).unwrap();
+        let data = DataSource::try_from(tmp).unwrap();
+        let id = data.create_issue("Issue", vec![], None).unwrap();
+        assert_eq!(data.closing_date(&id).unwrap(), None);
+
+        data.close_issue(&id).unwrap();
+        assert!(data.closing_date(&id).unwrap().is_some());
+        let states: Vec<State> = data
+            .state_changes()
+            .unwrap()
+            .iter()
+            .map(|c| *c.state())
+            .collect();
+        assert_eq!(states, vec![State::Open, State::Closed]);
+    }
+}
+
+#[cfg(test)]
+mod events {
+    use crate::{DataSource, EventKind};
+
+    #[test]
+    fn kinds() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let tmp = tmp_dir.path();
+        git_wrapper::setup_test_author();
+        crate::create(tmp, false).unwrap();
+        let data = DataSource::try_from(tmp).unwrap();
+        let id = data
+            .create_issue("Issue", vec!["bug".to_owned()], Some("v1".to_owned()))
+            .unwrap();
+        data.edit_description(&id, "Better title").unwrap();
+        data.remove_tag(&id, "bug").unwrap();
+        data.remove_milestone(&id).unwrap();
+        data.close_issue(&id).unwrap();
+
+        let events = data.events(Some(&id)).unwrap();
+        let kinds: Vec<&EventKind> = events.iter().map(|e| e.kind()).collect();
+        let tag = EventKind::TagAdded("bug".to_owned());
+        let milestone = EventKind::MilestoneSet(Some("v1".to_owned()));
+        // Without strict compatibility the issue is created in one commit, the events of a
+        // commit are ordered by file name
+        #[cfg(feature = "strict-compatibility")]
+        let created = [&tag, &milestone];
+        #[cfg(not(feature = "strict-compatibility"))]
+        let created = [&milestone, &tag];
+        assert_eq!(
+            kinds,
+            vec![
+                &EventKind::Created,
+                created[0],
+                created[1],
+                &EventKind::DescriptionEdited,
+                &EventKind::TagRemoved("bug".to_owned()),
+                &EventKind::MilestoneSet(None),
+                &EventKind::Closed,
+            ]
+        );
+        assert!(events.iter().all(|e| e.title() == "Better title"));
+    }
+}
+
+#[cfg(test)]
+mod issue_history {
+    use crate::{DataSource, DiffLine, Issue, Property};
+
+    #[test]
+    fn entries() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let tmp = tmp_dir.path();
+        git_wrapper::setup_test_author();
+        crate::create(tmp, false).unwrap();
+        let data = DataSource::try_from(tmp).unwrap();
+        let id = data
+            .create_issue("Title\n\nBody", vec!["bug".to_owned()], None)
+            .unwrap();
+        data.edit_description(&id, "Title\n\nBetter body").unwrap();
+        data.add_milestone(&id, "v1").unwrap();
+
+        let issue = Issue::new(&data, id);
+        let history = issue.history().unwrap();
+        let tags: Vec<_> = history
+            .iter()
+            .filter(|e| *e.property() == Property::Tags)
+            .collect();
+        #[cfg(feature = "strict-compatibility")]
+        assert_eq!(tags.last().unwrap().added_tags(), vec!["bug"]);
+        #[cfg(not(feature = "strict-compatibility"))]
+        assert_eq!(tags.last().unwrap().added_tags(), vec!["bug", "open"]);
+        assert!(tags.last().unwrap().removed_tags().is_empty());
+
+        let edit = history
+            .iter()
+            .rev()
+            .find(|e| *e.property() == Property::Description)
+            .unwrap();
+        assert_eq!(
+            edit.diff(),
+            vec![
+                DiffLine::Unchanged("Title".to_owned()),
+                DiffLine::Unchanged(String::new()),
+                DiffLine::Removed("Body".to_owned()),
+                DiffLine::Added("Better body".to_owned()),
+            ]
+        );
+
+        let milestone = history.last().unwrap();
+        assert_eq!(*milestone.property(), Property::Milestone);
+        assert_eq!(milestone.old(), &None);
+        assert_eq!(milestone.new().as_deref(), Some("v1"));
+    }
+}
+
+#[cfg(test)]
+mod revision {
+    use crate::{DataSource, Issue};
+
+    #[test]
+    fn read_at_revision() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let tmp = tmp_dir.path();
+        git_wrapper::setup_test_author();
+        crate::create(tmp, false).unwrap();
+        let data = DataSource::try_from(tmp).unwrap();
+        let first = data
+            .create_issue("First", vec!["bug".to_owned()], Some("v1".to_owned()))
+            .unwrap();
+        let rev = data.repo.head();
+        data.close_issue(&first).unwrap();
+        data.create_issue("Second", vec![], None).unwrap();
+
+        let old = DataSource::try_from(tmp).unwrap().at(&rev).unwrap();
+        assert_eq!(old.revision(), Some(rev.as_str()));
+        let ids: Vec<_> = old.all().map(|i| i.unwrap().id().clone()).collect();
+        assert_eq!(ids, vec![first.clone()]);
+        assert_eq!(old.find_issue(&first.id()[..4]).unwrap(), first);
+        assert_eq!(old.tags(&first), vec!["bug", "open"]);
+        assert_eq!(old.milestone(&first).as_deref(), Some("v1"));
+        assert_eq!(old.title(&first).unwrap(), "First");
+        assert!(Issue::new(&old, first.clone()).history().unwrap().len() >= 3);
+        assert!(old.add_tag(&first, "later").is_err());
+
+        assert!(DataSource::try_from(tmp)
+            .unwrap()
+            .at("no-such-rev")
+            .is_err());
+    }
+}
+
+#[cfg(test)]
+mod issue_store {
+    use std::path::Path;
+    use std::process::Command;
+
+    use crate::{DataSource, GitObjectStore, IssueStore, MemoryStore, Property};
+
+    #[test]
+    fn memory() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let data = crate::test_source(tmp_dir.path()).with_store(Box::new(MemoryStore::new()));
+        let id = data
+            .create_issue("In memory", vec!["bug".to_owned()], Some("v1".to_owned()))
+            .unwrap();
+        assert_eq!(data.find_issue(&id.id()[..4]).unwrap(), id);
+        assert_eq!(data.title(&id).unwrap(), "In memory");
+        assert_eq!(data.tags(&id), vec!["bug", "open"]);
+        data.remove_milestone(&id).unwrap();
+        assert_eq!(data.milestone(&id), None);
+        assert!(!id.path(&data.issues_dir).exists());
+    }
+
+    #[test]
+    fn bare_repository() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let tmp = tmp_dir.path();
+        let data = crate::test_source(&tmp.join("work"));
+        let first = data.create_issue("First", vec![], None).unwrap();
+        let out = Command::new("git")
+            .args(["clone", "--quiet", "--bare", "work/.issues", "bare.git"])
+            .current_dir(tmp)
+            .output()
+            .unwrap();
+        assert!(out.status.success());
+
+        let bare = DataSource::try_from_git_dir(&tmp.join("bare.git"), "HEAD", "").unwrap();
+        assert_eq!(bare.find_issue(first.id()).unwrap(), first);
+        let second = bare
+            .create_issue("Second", vec!["bug".to_owned()], None)
+            .unwrap();
+        bare.close_issue(&first).unwrap();
+        assert_eq!(bare.tags(&first), vec!["closed"]);
+        assert_eq!(bare.tags(&second), vec!["bug", "open"]);
+        assert_eq!(bare.all().count(), 2);
+        let description = bare.read(&second, &Property::Description).unwrap();
+        assert_eq!(description, "Second");
+    }
+
+    #[test]
+    fn concurrent_commits() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let data = crate::test_source(tmp_dir.path());
+        let id = data.create_issue("Title", vec![], None).unwrap();
+        let head = data.repo.head();
+
+        let refs = ["refs/heads/first", "refs/heads/second"];
+        std::thread::scope(|scope| {
+            for reference in refs {
+                let repo = data.repo.clone();
+                let (id, head) = (id.clone(), head.clone());
+                scope.spawn(move || {
+                    let args = ["update-ref", reference, &head];
+                    assert!(repo.git().args(args).status().unwrap().success());
+                    let store = GitObjectStore::new(repo, reference, "");
+                    for n in 0..10 {
+                        let field = Property::Field(format!("{}-{}", &reference[11..], n));
+                        store.write(&id, &field, Some("value")).unwrap();
+                        store.commit("gi: Set field", false).unwrap();
+                    }
+                });
+            }
+        });
+
+        for reference in refs {
+            let tree = format!("{}:{}", reference, id.path(Path::new("")).display());
+            let args = ["ls-tree", "--name-only", &tree];
+            let out = data.repo.git().args(args).output().unwrap();
+            let files = String::from_utf8_lossy(&out.stdout).to_string();
+            let fields: Vec<_> = files.lines().filter(|f| f.contains('-')).collect();
+            assert_eq!(fields.len(), 10, "Only the fields of {}", reference);
+        }
+        let out = data.repo.git().args(["rev-parse", "--git-dir"]).output();
+        let git_dir = data
+            .issues_dir
+            .join(String::from_utf8_lossy(&out.unwrap().stdout).trim());
+        let leftovers = std::fs::read_dir(git_dir)
+            .unwrap()
+            .filter(|e| {
+                let name = e.as_ref().unwrap().file_name();
+                name.to_string_lossy().starts_with("git-issue.index")
+            })
+            .count();
+        assert_eq!(leftovers, 0, "Temporary indexes are removed");
+    }
+}
+
+#[cfg(test)]
+mod change_sets {
+    use crate::{DataSource, WriteResult};
+
+    fn commit_count(data: &DataSource) -> usize {
+        let out = data.repo.git().args(["rev-list", "HEAD"]).output().unwrap();
+        String::from_utf8_lossy(&out.stdout).lines().count()
+    }
+
+    fn last_message(data: &DataSource) -> String {
+        let out = data
+            .repo
+            .git()
+            .args(["log", "-1", "--format=%B"])
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&out.stdout).trim_end().to_owned()
+    }
+
+    #[test]
+    fn create_issue() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let data = crate::test_source(tmp_dir.path());
+        let before = commit_count(&data);
+        let tags = vec!["bug".to_owned(), "ui".to_owned(), "easy".to_owned()];
+        let id = data
+            .create_issue("Title", tags, Some("v1".to_owned()))
+            .unwrap();
+        assert_eq!(data.tags(&id), vec!["bug", "easy", "open", "ui"]);
+        assert_eq!(data.milestone(&id).as_deref(), Some("v1"));
+
+        #[cfg(feature = "strict-compatibility")]
+        assert_eq!(commit_count(&data) - before, 6, "One commit per property");
+        #[cfg(not(feature = "strict-compatibility"))]
+        {
+            assert_eq!(commit_count(&data) - before, 2, "Mark & properties");
+            let message = last_message(&data);
+            assert_eq!(
+                message.lines().next().unwrap(),
+                format!(
+                    "gi({}): Add description, Add tag open, Add tag bug, Add tag ui, \
+                     Add tag easy, Add milestone v1",
+                    id.short_id()
+                )
+            );
+            assert!(message.contains(&format!("\ngi new description {}\n", id.id())));
+            assert!(message.ends_with("gi tag add easy\ngi milestone add v1"));
+        }
+    }
+
+    #[test]
+    fn redundant_changes() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let data = crate::test_source(tmp_dir.path());
+        let first = data.create_issue("First", vec![], None).unwrap();
+        let second = data.create_issue("Second", vec![], None).unwrap();
+        let before = commit_count(&data);
+
+        let result = data
+            .change_set()
+            .add_tag(&first, "open")
+            .remove_milestone(&second)
+            .commit()
+            .unwrap();
+        assert_eq!(result, WriteResult::NoChanges);
+        assert_eq!(commit_count(&data), before);
+
+        let result = data
+            .change_set()
+            .add_tag(&first, "bug")
+            .add_tag(&first, "bug")
+            .commit()
+            .unwrap();
+        assert_eq!(result, WriteResult::Applied);
+        assert_eq!(commit_count(&data), before + 1);
+        #[cfg(feature = "strict-compatibility")]
+        assert_eq!(last_message(&data), "gi: Add tag\n\ngi tag add bug");
+        #[cfg(not(feature = "strict-compatibility"))]
+        assert_eq!(
+            last_message(&data),
+            format!("gi({}): Add tag bug\n\ngi tag add bug", first.short_id())
+        );
+
+        let result = data
+            .change_set()
+            .add_milestone(&first, "v1")
+            .add_milestone(&second, "v1")
+            .commit()
+            .unwrap();
+        assert_eq!(result, WriteResult::Applied);
+        assert_eq!(data.milestone(&second).as_deref(), Some("v1"));
+        #[cfg(not(feature = "strict-compatibility"))]
+        assert_eq!(
+            last_message(&data),
+            "gi: Update 2 issues\n\ngi milestone add v1\ngi milestone add v1"
+        );
+    }
+}
+
+#[cfg(test)]
+mod transaction_guard {
+    use crate::DataSource;
+
+    fn head(data: &DataSource) -> String {
+        data.repo.head()
+    }
+
+    #[test]
+    fn rollback_on_drop() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let data = crate::test_source(tmp_dir.path());
+        let id = data.create_issue("Title", vec![], None).unwrap();
+        let start = head(&data);
+        let config = data.issues_dir.join("config");
+        std::fs::write(&config, "# Uncommitted\n").unwrap();
+
+        {
+            let _transaction = data.transaction().unwrap();
+            assert!(data.repo.is_clean(), "Changes are stashed");
+            data.add_tag(&id, "bug").unwrap();
+            assert_ne!(head(&data), start);
+        }
+        assert_eq!(head(&data), start);
+        assert_eq!(data.tags(&id), vec!["open"]);
+        assert_eq!(std::fs::read_to_string(&config).unwrap(), "# Uncommitted\n");
+    }
+
+    #[test]
+    fn rollback_on_panic() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let data = crate::test_source(tmp_dir.path());
+        let id = data.create_issue("Title", vec![], None).unwrap();
+        let start = head(&data);
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _transaction = data.transaction().unwrap();
+            data.add_tag(&id, "bug").unwrap();
+            panic!("Mid-operation");
+        }));
+        assert!(result.is_err());
+        assert_eq!(head(&data), start);
+        assert_eq!(data.tags(&id), vec!["open"]);
+    }
+
+    #[test]
+    fn commit() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let data = crate::test_source(tmp_dir.path());
+        let id = data.create_issue("Title", vec![], None).unwrap();
+        let transaction = data.transaction().unwrap();
+        data.add_tag(&id, "bug").unwrap();
+        transaction.commit("gi: Tag it").unwrap();
+        assert_eq!(data.tags(&id), vec!["bug", "open"]);
+
+        let out = data
+            .repo
+            .git()
+            .args(["log", "-1", "--format=%s%n%P"])
+            .output()
+            .unwrap();
+        let log = String::from_utf8_lossy(&out.stdout).to_string();
+        #[cfg(not(feature = "strict-compatibility"))]
+        {
+            let mut lines = log.lines();
+            assert_eq!(lines.next(), Some("gi: Tag it"));
+            assert_eq!(lines.next().unwrap().split(' ').count(), 2, "Merge commit");
+        }
+        #[cfg(feature = "strict-compatibility")]
+        assert!(log.starts_with("gi: Add tag\n"), "No merge commit");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rollback_on_failed_merge() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let data = crate::test_source(tmp_dir.path());
+        let id = data.create_issue("Title", vec![], None).unwrap();
+        let start = head(&data);
+        let config = data.issues_dir.join("config");
+        std::fs::write(&config, "# Uncommitted\n").unwrap();
+
+        let out = data
+            .repo
+            .git()
+            .args(["rev-parse", "--git-path", "hooks/pre-merge-commit"])
+            .output()
+            .unwrap();
+        let hook = data
+            .issues_dir
+            .join(String::from_utf8_lossy(&out.stdout).trim());
+        std::fs::create_dir_all(hook.parent().unwrap()).unwrap();
+        std::fs::write(&hook, "#!/bin/sh\nexit 1\n").unwrap();
+        std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let data = data.with_strategy(crate::MergeStrategy::MergeNoFf);
+        let transaction = data.transaction().unwrap();
+        data.add_tag(&id, "bug").unwrap();
+        assert!(transaction.commit("gi: Tag it").is_err());
+
+        assert_eq!(head(&data), start);
+        assert_eq!(data.tags(&id), vec!["open"]);
+        assert_eq!(std::fs::read_to_string(&config).unwrap(), "# Uncommitted\n");
+        let out = data.repo.git().args(["stash", "list"]).output().unwrap();
+        assert!(out.stdout.is_empty(), "No pending stash");
+    }
+}
+
+#[cfg(test)]
+mod merge_strategies {
+    use crate::{DataSource, MergeStrategy};
+
+    fn log(data: &DataSource, range: &str) -> Vec<String> {
+        let out = data
+            .repo
+            .git()
+            .args(["log", "--format=%s", range])
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&out.stdout)
+            .lines()
+            .map(ToOwned::to_owned)
+            .collect()
+    }
+
+    fn tag_twice(data: &DataSource) -> String {
+        let id = data.create_issue("Title", vec![], None).unwrap();
+        let start = data.repo.head();
+        let transaction = data.transaction().unwrap();
+        data.add_tag(&id, "bug").unwrap();
+        data.add_tag(&id, "ui").unwrap();
+        transaction.commit("gi: Tag it").unwrap();
+        assert_eq!(data.tags(&id), vec!["bug", "open", "ui"]);
+        start
+    }
+
+    #[test]
+    fn fast_forward() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let data = crate::test_source(tmp_dir.path()).with_strategy(MergeStrategy::FastForward);
+        let start = tag_twice(&data);
+        let subjects = log(&data, &format!("{}..HEAD", start));
+        assert_eq!(subjects.len(), 2);
+        assert!(subjects.iter().all(|s| s != "gi: Tag it"));
+    }
+
+    #[test]
+    fn squash() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let data = crate::test_source(tmp_dir.path()).with_strategy(MergeStrategy::Squash);
+        let start = tag_twice(&data);
+        assert_eq!(log(&data, &format!("{}..HEAD", start)), vec!["gi: Tag it"]);
+        assert_eq!(log(&data, "-1")[0], "gi: Tag it");
+        let parent = data
+            .repo
+            .git()
+            .args(["rev-parse", "HEAD^"])
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&parent.stdout).trim(), start);
+    }
+
+    #[test]
+    fn squash_canceled_out() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let data = crate::test_source(tmp_dir.path()).with_strategy(MergeStrategy::Squash);
+        let id = data.create_issue("Title", vec![], None).unwrap();
+        let start = data.repo.head();
+        std::fs::write(data.issues_dir.join("config"), "# Uncommitted\n").unwrap();
+        let transaction = data.transaction().unwrap();
+        data.add_tag(&id, "bug").unwrap();
+        data.remove_tag(&id, "bug").unwrap();
+        transaction.commit("gi: Tag & untag it").unwrap();
+
+        assert_eq!(data.repo.head(), start, "Nothing to commit");
+        assert_eq!(data.tags(&id), vec!["open"]);
+        let stashes = log(&data, "--walk-reflogs=refs/stash");
+        assert!(stashes.is_empty(), "No pending stash");
+    }
+
+    #[test]
+    fn merge_no_ff() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let data = crate::test_source(tmp_dir.path()).with_strategy(MergeStrategy::MergeNoFf);
+        let start = tag_twice(&data);
+        let subjects = log(&data, &format!("{}..HEAD", start));
+        assert_eq!(subjects.len(), 3, "Merge & both tag commits");
+        assert_eq!(subjects[0], "gi: Tag it");
+    }
+
+    #[test]
+    fn config() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let data = crate::test_source(tmp_dir.path());
+        assert_eq!(data.merge_strategy(), MergeStrategy::default());
+        #[cfg(feature = "strict-compatibility")]
+        assert_eq!(MergeStrategy::default(), MergeStrategy::FastForward);
+        #[cfg(not(feature = "strict-compatibility"))]
+        assert_eq!(MergeStrategy::default(), MergeStrategy::MergeNoFf);
+
+        let config = data.issues_dir.join("config");
+        let set = |value: &str| {
+            let file = config.to_str().unwrap();
+            let args = ["config", "-f", file, "issues.strategy", value];
+            assert!(data.repo.git().args(args).status().unwrap().success());
+        };
+        set("squash");
+        assert_eq!(data.merge_strategy(), MergeStrategy::Squash);
+        set("no-such-strategy");
+        assert_eq!(data.merge_strategy(), MergeStrategy::default());
+
+        set("squash");
+        let data = data.with_strategy(MergeStrategy::FastForward);
+        assert_eq!(data.merge_strategy(), MergeStrategy::FastForward);
+    }
+}
+
+#[cfg(test)]
+mod ref_mode {
+    use time::format_description::well_known::Rfc3339;
+
+    use crate::{DataSource, GitObjectStore, InitError, IssueStore, MergeStrategy};
+
+    const REF: &str = "refs/heads/issues";
+
+    fn git(data: &DataSource, args: &[&str]) -> String {
+        let out = data.repo.git().args(args).output().unwrap();
+        String::from_utf8_lossy(&out.stdout).trim().to_owned()
+    }
+
+    /// Leave staged, unstaged & untracked changes in the working tree
+    fn dirty(data: &DataSource) -> String {
+        std::fs::write(data.issues_dir.join("README.md"), "Staged\n").unwrap();
+        git(data, &["add", "README.md"]);
+        std::fs::write(data.issues_dir.join("README.md"), "Unstaged\n").unwrap();
+        std::fs::write(data.issues_dir.join("notes"), "Untracked\n").unwrap();
+        git(data, &["status", "--porcelain"])
+    }
+
+    #[test]
+    fn working_tree_untouched() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let data = crate::test_source(tmp_dir.path());
+        let old = data.create_issue("Old", vec![], None).unwrap();
+        let head = data.repo.head();
+        let status = dirty(&data);
+
+        let data = data.with_ref(REF).unwrap();
+        assert_eq!(git(&data, &["rev-parse", REF]), head, "Created from HEAD");
+        let transaction = data.transaction().unwrap();
+        let id = data
+            .create_issue("New", vec!["bug".to_owned()], None)
+            .unwrap();
+        data.close_issue(&old).unwrap();
+        transaction.commit("gi: New & close").unwrap();
+
+        assert_eq!(data.repo.head(), head);
+        assert_eq!(git(&data, &["status", "--porcelain"]), status);
+        assert!(git(&data, &["stash", "list"]).is_empty());
+        assert!(!id.path(&data.issues_dir).exists());
+        assert_eq!(data.tags(&id), vec!["bug", "open"]);
+        assert_eq!(data.tags(&old), vec!["closed"]);
+        assert_eq!(data.find_issue(id.short_id()).unwrap(), id);
+        assert_eq!(data.find_issue(id.id()).unwrap(), id);
+
+        let subject = git(&data, &["log", "-1", "--format=%s", REF]);
+        #[cfg(not(feature = "strict-compatibility"))]
+        assert_eq!(subject, "gi: New & close");
+        #[cfg(feature = "strict-compatibility")]
+        assert_ne!(subject, "gi: New & close");
+    }
+
+    #[test]
+    fn rollback() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let data = crate::test_source(tmp_dir.path());
+        let id = data.create_issue("Title", vec![], None).unwrap();
+        let status = dirty(&data);
+        let data = data.with_ref(REF).unwrap();
+        let start = git(&data, &["rev-parse", REF]);
+        {
+            let _transaction = data.transaction().unwrap();
+            data.add_tag(&id, "bug").unwrap();
+            assert_ne!(git(&data, &["rev-parse", REF]), start);
+        }
+        assert_eq!(git(&data, &["rev-parse", REF]), start);
+        assert_eq!(data.tags(&id), vec!["open"]);
+        assert_eq!(git(&data, &["status", "--porcelain"]), status);
+    }
+
+    #[test]
+    fn squash() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let data = crate::test_source(tmp_dir.path())
+            .with_ref(REF)
+            .unwrap()
+            .with_strategy(MergeStrategy::Squash);
+        let id = data.create_issue("Title", vec![], None).unwrap();
+        let start = git(&data, &["rev-parse", REF]);
+        let transaction = data.transaction().unwrap();
+        data.add_tag(&id, "bug").unwrap();
+        data.add_tag(&id, "ui").unwrap();
+        transaction.commit("gi: Tag it").unwrap();
+
+        let range = format!("{}..{}", start, REF);
+        assert_eq!(git(&data, &["log", "--format=%s", &range]), "gi: Tag it");
+        assert_eq!(data.tags(&id), vec!["bug", "open", "ui"]);
+    }
+
+    #[test]
+    fn squash_canceled_out() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let data = crate::test_source(tmp_dir.path())
+            .with_ref(REF)
+            .unwrap()
+            .with_strategy(MergeStrategy::Squash);
+        let id = data.create_issue("Title", vec![], None).unwrap();
+        let start = git(&data, &["rev-parse", REF]);
+        let transaction = data.transaction().unwrap();
+        data.add_tag(&id, "bug").unwrap();
+        data.remove_tag(&id, "bug").unwrap();
+        transaction.commit("gi: Tag & untag it").unwrap();
+
+        assert_eq!(git(&data, &["rev-parse", REF]), start, "Nothing to commit");
+        assert_eq!(data.tags(&id), vec!["open"]);
+    }
+
+    #[test]
+    fn history() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let data = crate::test_source(tmp_dir.path()).with_ref(REF).unwrap();
+        let id = data.create_issue("Title", vec![], None).unwrap();
+        data.add_tag(&id, "bug").unwrap();
+        assert_ne!(git(&data, &["rev-parse", REF]), data.repo.head());
+
+        let issue = crate::Issue::new(&data, id.clone());
+        let history = issue.history().unwrap();
+        assert!(history.iter().any(|e| e.added_tags() == vec!["bug"]));
+        let kinds: Vec<_> = data
+            .events(Some(&id))
+            .unwrap()
+            .into_iter()
+            .map(|e| e.kind().clone())
+            .collect();
+        assert_eq!(kinds[0], crate::EventKind::Created);
+        assert_eq!(
+            kinds.last(),
+            Some(&crate::EventKind::TagAdded("bug".to_owned()))
+        );
+
+        let comment = crate::Property::Comment("c0ffee".to_owned());
+        let store = GitObjectStore::new(data.repo.clone(), REF, "");
+        store.write(&id, &comment, Some("Looks good")).unwrap();
+        store.commit("gi: Add comment", false).unwrap();
+        let date = git(&data, &["log", "-1", "--format=%aI", REF]);
+        let date = time::OffsetDateTime::parse(&date, &Rfc3339).unwrap();
+        let comments = data.comments(&id);
+        assert_eq!(*comments[0].as_ref().unwrap().cdate(), date);
+
+        let mut issue = issue;
+        assert_eq!(*issue.cache_mdate().unwrap().mdate(), date);
+    }
+
+    #[test]
+    fn invalid_reference() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let data = crate::test_source(tmp_dir.path());
+        assert!(matches!(
+            data.with_ref("issues"),
+            Err(InitError::RevisionNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn checked_out_reference() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let data = crate::test_source(tmp_dir.path());
+        let branch = git(&data, &["symbolic-ref", "HEAD"]);
+        let linked = tmp_dir.path().join("linked");
+        let args = [
+            "worktree",
+            "add",
+            "--quiet",
+            "-b",
+            "linked",
+            linked.to_str().unwrap(),
+        ];
+        git(&data, &args);
+        let head = data.repo.head();
+
+        let open = || DataSource::try_from(tmp_dir.path()).unwrap();
+        let short = branch.trim_start_matches("refs/heads/");
+        for reference in ["HEAD", &branch, short, "refs/heads/linked"] {
+            assert!(
+                matches!(open().with_ref(reference), Err(InitError::CheckedOut(_))),
+                "{} is rejected",
+                reference
+            );
+        }
+
+        let config = open().issues_dir.join("config");
+        let set = |value: &str| {
+            let file = config.to_str().unwrap();
+            git(&open(), &["config", "-f", file, "issues.ref", value]);
+        };
+        set("HEAD");
+        assert!(matches!(
+            open().with_configured_ref(),
+            Err(InitError::CheckedOut(_))
+        ));
+        set(REF);
+        assert_eq!(
+            open().with_configured_ref().unwrap().commit_ref(),
+            Some(REF)
+        );
+        assert_eq!(open().repo.head(), head, "Checked out branch is untouched");
+    }
+}
+
+#[cfg(test)]
+mod creation_info {
+    use crate::{Id, Issue};
+
+    #[test]
+    fn batch() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let data = crate::test_source(tmp_dir.path());
+        let first = data.create_issue("First", vec![], None).unwrap();
+        let second = data.create_issue("Second", vec![], None).unwrap();
+        let missing = Id::new("0".repeat(40));
+
+        let infos = data.creation_infos([&first, &second, &missing]).unwrap();
+        assert_eq!(infos.len(), 2);
+        assert_eq!(*infos[&first].date(), data.creation_date(&first).unwrap());
+        assert_eq!(infos[&second].author_name(), "Max Musterman");
+
+        let mut issues = vec![Issue::new(&data, first), Issue::new(&data, second)];
+        Issue::cache_creation(&mut issues).unwrap();
+        assert_eq!(issues[0].author_email(), "max@example.com");
+        assert_eq!(*issues[1].cdate(), *infos[issues[1].id()].date());
+    }
+}
+
+#[cfg(test)]
+mod par_map {
+    use crate::{par_map, DataSource, Issue};
+
+    fn assert_send<T: Send>() {}
+    fn assert_sync<T: Sync>() {}
+
+    #[test]
+    fn shareable() {
+        assert_send::<Issue<'_>>();
+        assert_sync::<DataSource>();
+    }
+
+    #[test]
+    fn keeps_order() {
+        let input: Vec<usize> = (0..1000).collect();
+        let expected: Vec<usize> = input.iter().map(|n| n * 2).collect();
+        assert_eq!(par_map(input, |n| n * 2), expected);
+    }
+
+    #[test]
+    fn loads_issues() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let data = crate::test_source(tmp_dir.path());
+        for n in 0..70 {
+            let tags = if n % 2 == 0 {
+                vec!["even".to_owned()]
+            } else {
+                vec![]
+            };
+            data.create_issue(&format!("Issue {}", n), tags, None)
+                .unwrap();
+        }
+        let issues: Vec<Issue<'_>> = data.all().map(Result::unwrap).collect();
+        let ids: Vec<_> = issues.iter().map(|i| i.id().clone()).collect();
+        let cached = par_map(issues, |mut i| {
+            i.cache_tags().unwrap();
+            i
+        });
+        assert_eq!(
+            cached.iter().map(|i| i.id().clone()).collect::<Vec<_>>(),
+            ids
+        );
+        let even = cached
+            .iter()
+            .filter(|i| i.tags().contains(&"even".to_owned()));
+        assert_eq!(even.count(), 35);
+    }
+}
+
+#[cfg(test)]
+mod disk_cache {
+    use crate::Issue;
+
+    #[test]
+    fn invalidated_by_changes() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let data = crate::test_source(tmp_dir.path());
+        let first = data
+            .create_issue("First", vec!["bug".to_owned()], None)
+            .unwrap();
+        data.create_issue("Second", vec![], None).unwrap();
+        let cached = |data: &crate::DataSource| {
+            let mut issues: Vec<Issue<'_>> = data.all().map(Result::unwrap).collect();
+            Issue::cache_metadata(&mut issues).unwrap();
+            issues
+                .iter()
+                .map(|i| (i.title(), i.tags().clone(), i.comment_count()))
+                .collect::<Vec<_>>()
+        };
+
+        let fresh = cached(&data);
+        let path = data.issues_dir.join(".git/git-issue/cache");
+        let text = std::fs::read_to_string(&path).expect("Written cache");
+        assert_eq!(text.lines().count(), 3);
+
+        // Unchanged issues are served from the cache file
+        std::fs::write(&path, text.replace("\tFirst\t", "\tCached\t")).unwrap();
+        assert_eq!(cached(&data).len(), fresh.len());
+        assert!(cached(&data).iter().any(|(title, _, _)| title == "Cached"));
+
+        // Uncommitted & committed changes invalidate the entry
+        let tags = first.path(&data.issues_dir).join("tags");
+        std::fs::write(&tags, "bug\nopen\nwip\n").unwrap();
+        let dirty = cached(&data);
+        assert!(dirty
+            .iter()
+            .any(|(title, tags, _)| title == "First" && tags.len() == 3));
+        data.close_issue(&first).unwrap();
+        let closed = cached(&data);
+        assert!(closed
+            .iter()
+            .any(|(title, tags, _)| title == "First" && tags.contains(&"closed".to_owned())));
+        assert!(!std::fs::read_to_string(&path).unwrap().contains("Cached"));
+    }
+
+    #[test]
+    fn drops_removed_issues() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let data = crate::test_source(tmp_dir.path());
+        let first = data.create_issue("First", vec![], None).unwrap();
+        let second = data.create_issue("Second", vec![], None).unwrap();
+        let cache = |data: &crate::DataSource| {
+            let mut issues: Vec<Issue<'_>> = data.all().map(Result::unwrap).collect();
+            Issue::cache_metadata(&mut issues).unwrap();
+        };
+        cache(&data);
+        let dir = data.issues_dir.join(".git/git-issue");
+        let text = std::fs::read_to_string(dir.join("cache")).unwrap();
+        assert!(text.contains(first.id()) && text.contains(second.id()));
+
+        let path = first.path(&data.issues_dir);
+        let args = ["rm", "-r", "--quiet", path.to_str().unwrap()];
+        assert!(data.repo.git().args(args).status().unwrap().success());
+        let args = ["commit", "--quiet", "-m", "gi: Remove issue"];
+        assert!(data.repo.git().args(args).status().unwrap().success());
+        cache(&data);
+        let text = std::fs::read_to_string(dir.join("cache")).unwrap();
+        assert!(!text.contains(first.id()), "Removed issue is dropped");
+        assert!(text.contains(second.id()));
+
+        let files: Vec<_> = std::fs::read_dir(&dir).unwrap().collect();
+        assert_eq!(files.len(), 1, "No temporary files are left");
+    }
+}
+
+#[cfg(test)]
+mod object_reader {
+    use crate::objects::Objects;
+    use crate::Property;
+
+    #[test]
+    fn commits_trees_and_blobs() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let data = crate::test_source(tmp_dir.path());
+        let id = data
+            .create_issue("Title\n\nBody", vec!["bug".to_owned()], None)
+            .unwrap();
+        let objects = Objects::new(&data.repo);
+        assert_eq!(objects.is_native(), cfg!(feature = "native-git"));
+
+        let missing = "0".repeat(40);
+        let authors = objects.authors([id.id().as_str(), &missing]).unwrap();
+        assert_eq!(authors.len(), 1);
+        let (date, name, email) = &authors[id.id()];
+        assert_eq!(*date, data.creation_date(&id).unwrap());
+        assert_eq!(
+            (name.as_str(), email.as_str()),
+            ("Max Musterman", "max@example.com")
+        );
+
+        let entries = objects.ls_tree("HEAD:issues");
+        let dir = format!("{}/{}", &id.id()[..2], &id.id()[2..]);
+        assert!(entries.iter().any(|e| e.is_tree && e.path == dir));
+        let tags = entries
+            .iter()
+            .find(|e| e.path == format!("{}/tags", dir))
+            .expect("Listed tags blob");
+        assert!(!tags.is_tree);
+
+        let blobs = objects.blobs([&tags.sha].into_iter()).unwrap();
+        assert_eq!(blobs[&tags.sha], "bug\nopen");
+        let description = objects.blob(&format!("HEAD:issues/{}/description", dir));
+        assert_eq!(
+            description.unwrap().map(|d| d.trim_end().to_owned()),
+            data.read(&id, &Property::Description).ok()
+        );
+        assert_eq!(objects.blob("HEAD:missing").unwrap(), None);
+        assert!(objects.ls_tree("missing").is_empty());
+    }
+
+    #[test]
+    fn last_changes() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let data = crate::test_source(tmp_dir.path());
+        let id = data.create_issue("Title", vec![], None).unwrap();
+        let dir = id.path(&data.issues_dir).join("comments");
+        std::fs::create_dir_all(&dir).unwrap();
+        let commit = |file: &str, author: &str| {
+            std::fs::write(dir.join(file), "Comment").unwrap();
+            let git = |args: &[&str]| data.repo.git().args(args).status().unwrap().success();
+            assert!(git(&["add", dir.join(file).to_str().unwrap()]));
+            assert!(git(&["commit", "--quiet", "-m", file, "--author", author]));
+        };
+        commit("aaa", "Max Musterman <max@example.com>");
+        commit("bbb", "Erika Musterfrau <erika@example.com>");
+        std::fs::write(dir.join("ccc"), "Uncommitted").unwrap();
+
+        let objects = Objects::new(&data.repo);
+        let authors = objects
+            .last_changes(None, &dir, &["aaa", "bbb", "ccc"])
+            .unwrap();
+        assert_eq!(authors.len(), 2);
+        assert_eq!(authors["aaa"].1, "Max Musterman");
+        assert_eq!(authors["bbb"].2, "erika@example.com");
+        let first = objects.last_changes(Some("HEAD^"), &dir, &["aaa", "bbb"]);
+        assert_eq!(first.unwrap().len(), 1, "Read from the given revision");
+    }
+}
+
+#[cfg(test)]
+mod load_diagnostics {
+    #[test]
+    fn invalid_issues() {
+        let tmp_dir = tempfile::TempDir::new().unwrap();
+        let data = crate::test_source(tmp_dir.path());
+        let id = data.create_issue("Valid", vec![], None).unwrap();
+        let invalid = data.issues_dir.join("issues").join("zz").join("invalid");
+        std::fs::create_dir_all(&invalid).unwrap();
+
+        let (issues, errors) = data.all_with_diagnostics();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(*errors[0].path(), invalid);
+        assert!(errors[0].to_string().contains("Invalid issue id zzinvalid"));
+
+        // Multi-byte directory names are not split inside a character
+        let euro = data.issues_dir.join("issues").join("€").join("abc");
+        std::fs::create_dir_all(&euro).unwrap();
+        let (_, errors) = data.all_with_diagnostics();
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().any(|e| *e.path() == euro));
+
+        // Broken files are reported as errors instead of panics
+        std::fs::write(id.path(&data.issues_dir).join("duedate"), "tomorrow").unwrap();
+        let mut issue = issues.into_iter().next().unwrap();
+        assert!(issue.cache_ddate().is_err());
+        assert_eq!(issue.path(), id.path(&data.issues_dir));
+        let error = data.duedate(&id).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        let missing = crate::Id::new("0".repeat(40));
+        assert!(data.creation_date(&missing).is_err());
+    }
+}
@@ -0,0 +1,466 @@
+//! Read commits, trees & blobs from the object database
+//!
+//! With the `native-git` feature the objects are read in-process with libgit2, otherwise and if
+//! libgit2 fails to open the repository git(1) is spawned.
+
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::Stdio;
+
+use git_wrapper::Repository;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::caching::CacheError;
+
+/// Sha of a missing object, e.g. the old blob of an added file
+const NULL_SHA: &str = "0000000000000000000000000000000000000000";
+
+/// Author date, name & email of a commit
+pub(crate) type Author = (OffsetDateTime, String, String);
+
+/// An entry of a tree listed recursively
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct TreeEntry {
+    /// Path relative to the listed tree
+    pub path: String,
+    /// Object sha
+    pub sha: String,
+    /// `true` for trees, `false` for blobs
+    pub is_tree: bool,
+}
+
+/// Reader for git objects of a repository
+pub(crate) struct Objects {
+    repo: Repository,
+    #[cfg(feature = "native-git")]
+    native: Option<std::sync::Mutex<git2::Repository>>,
+}
+
+#[cfg(not(tarpaulin_include))]
+impl std::fmt::Debug for Objects {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Objects")
+            .field("repo", &self.repo)
+            .field("native", &self.is_native())
+            .finish()
+    }
+}
+
+fn invalid_data(msg: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, msg)
+}
+
+/// Run git(1) with the given input on stdin and return its stdout
+fn pipe(mut cmd: std::process::Command, input: String) -> std::io::Result<Vec<u8>> {
+    let mut child = cmd.stdin(Stdio::piped()).stdout(Stdio::piped()).spawn()?;
+    let mut stdin = child.stdin.take().expect("Piped stdin");
+    // Write from a separate thread, so a full stdout pipe can not dead lock us
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let mut output = vec![];
+    child
+        .stdout
+        .take()
+        .expect("Piped stdout")
+        .read_to_end(&mut output)?;
+    writer.join().map_err(|_err| {
+        std::io::Error::new(std::io::ErrorKind::BrokenPipe, "Failed to write to git(1)")
+    })??;
+    child.wait()?;
+    Ok(output)
+}
+
+impl Objects {
+    /// Create a reader for the repository, the git directory is the one git(1) would use
+    pub fn new(repo: &Repository) -> Self {
+        Self {
+            repo: repo.clone(),
+            #[cfg(feature = "native-git")]
+            native: native::open(repo).map(std::sync::Mutex::new),
+        }
+    }
+
+    /// Return `true` if objects are read with libgit2
+    pub const fn is_native(&self) -> bool {
+        #[cfg(feature = "native-git")]
+        return self.native.is_some();
+        #[cfg(not(feature = "native-git"))]
+        false
+    }
+
+    /// Return the author of the given commits, shas which are not commits are skipped
+    ///
+    /// # Errors
+    ///
+    /// Will throw an error if executing git(1) fails or it returns an invalid date
+    pub fn authors<'a>(
+        &self,
+        shas: impl IntoIterator<Item = &'a str>,
+    ) -> Result<HashMap<String, Author>, CacheError> {
+        #[cfg(feature = "native-git")]
+        if let Some(native) = &self.native {
+            return Ok(native::authors(&native::lock(native), shas));
+        }
+        let input: String = shas.into_iter().map(|sha| format!("{}\n", sha)).collect();
+        if input.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let mut cmd = self.repo.git();
+        cmd.args([
+            "log",
+            "--no-walk=unsorted",
+            "--ignore-missing",
+            "--stdin",
+            "--format=%H%x09%aI%x09%aN%x09%aE",
+        ]);
+        let output = pipe(cmd, input)?;
+
+        let mut result = HashMap::new();
+        for line in String::from_utf8_lossy(&output).lines() {
+            let mut split = line.splitn(4, '\t');
+            let mut next = || split.next().unwrap_or_default().to_owned();
+            let (sha, date, name, email) = (next(), next(), next(), next());
+            result.insert(sha, (OffsetDateTime::parse(&date, &Rfc3339)?, name, email));
+        }
+        Ok(result)
+    }
+
+    /// Return the author of the newest commit reachable from `rev` changing each of the `files`
+    /// in `dir`, files which were never committed are missing from the result
+    ///
+    /// `rev` defaults to `HEAD`. Without libgit2 a single git-log(1) call is used.
+    ///
+    /// # Errors
+    ///
+    /// Will throw an error if executing git(1) fails or it returns an invalid date
+    pub fn last_changes(
+        &self,
+        rev: Option<&str>,
+        dir: &Path,
+        files: &[&str],
+    ) -> Result<HashMap<String, Author>, CacheError> {
+        if files.is_empty() {
+            return Ok(HashMap::new());
+        }
+        #[cfg(feature = "native-git")]
+        if let Some(native) = &self.native {
+            let native = native::lock(native);
+            if let Some(result) = native::last_changes(&native, rev, dir, files) {
+                return Ok(result);
+            }
+        }
+        let out = self
+            .repo
+            .git()
+            .args([
+                "log",
+                "--name-only",
+                "--no-renames",
+                "--format=commit %aI%x09%aN%x09%aE",
+            ])
+            .args(rev)
+            .arg("--")
+            .arg(dir)
+            .output()?;
+
+        let mut result = HashMap::new();
+        let mut author: Option<Author> = None;
+        for line in String::from_utf8_lossy(&out.stdout).lines() {
+            if let Some(header) = line.strip_prefix("commit ") {
+                let mut split = header.splitn(3, '\t');
+                let mut next = || split.next().unwrap_or_default().to_owned();
+                let (date, name, email) = (next(), next(), next());
+                author = Some((OffsetDateTime::parse(&date, &Rfc3339)?, name, email));
+                continue;
+            }
+            let name = line.rsplit('/').next().unwrap_or_default();
+            if let (Some(file), Some(author)) = (files.iter().find(|f| **f == name), &author) {
+                result
+                    .entry((*file).to_owned())
+                    .or_insert_with(|| author.clone());
+            }
+        }
+        Ok(result)
+    }
+
+    /// Read the content of all given blobs, the trailing white space is removed
+    ///
+    /// Without libgit2 a single git-cat-file(1) call is used. Missing blobs are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Will throw an error if executing git(1) fails or its output is invalid
+    pub fn blobs<'a>(
+        &self,
+        shas: impl Iterator<Item = &'a String>,
+    ) -> std::io::Result<HashMap<String, String>> {
+        let unique: HashSet<&String> = shas.filter(|sha| *sha != NULL_SHA).collect();
+        if unique.is_empty() {
+            return Ok(HashMap::new());
+        }
+        #[cfg(feature = "native-git")]
+        if let Some(native) = &self.native {
+            return Ok(native::blobs(&native::lock(native), unique));
+        }
+        let input: String = unique.iter().map(|sha| format!("{}\n", sha)).collect();
+        let mut cmd = self.repo.git();
+        cmd.args(["cat-file", "--batch"]);
+        let output = pipe(cmd, input)?;
+
+        let mut result = HashMap::new();
+        let mut rest = output.as_slice();
+        while let Some(end) = rest.iter().position(|b| *b == b'\n') {
+            let header = String::from_utf8_lossy(&rest[..end]).to_string();
+            rest = &rest[end.saturating_add(1)..];
+            let fields: Vec<&str> = header.split(' ').collect();
+            if let [sha, _kind, size] = fields[..] {
+                let size: usize = size
+                    .parse()
+                    .map_err(|_err| invalid_data(format!("Invalid header {}", header)))?;
+                let body = rest.get(..size).ok_or_else(|| {
+                    invalid_data(format!("Truncated git-cat-file(1) output for {}", sha))
+                })?;
+                result.insert(
+                    sha.to_owned(),
+                    String::from_utf8_lossy(body).trim_end().to_owned(),
+                );
+                // Skip the body and the trailing new line
+                rest = rest.get(size.saturating_add(1)..).unwrap_or_default();
+            } else {
+                log::warn!("Missing blob: {}", header);
+            }
+        }
+        Ok(result)
+    }
+
+    /// Read a blob by its `<tree-ish>:<path>` name, `None` if it does not exist
+    ///
+    /// # Errors
+    ///
+    /// Will throw an error if executing git(1) fails
+    pub fn blob(&self, name: &str) -> std::io::Result<Option<String>> {
+        #[cfg(feature = "native-git")]
+        if let Some(native) = &self.native {
+            return Ok(native::blob(&native::lock(native), name));
+        }
+        let out = self.repo.git().args(["cat-file", "blob", name]).output()?;
+        Ok(out
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&out.stdout).to_string()))
+    }
+
+    /// List a tree recursively, an unknown tree is empty
+    pub fn ls_tree(&self, treeish: &str) -> Vec<TreeEntry> {
+        #[cfg(feature = "native-git")]
+        if let Some(native) = &self.native {
+            return native::ls_tree(&native::lock(native), treeish);
+        }
+        let out = match self
+            .repo
+            .git()
+            .args(["ls-tree", "-r", "-t", "-z", treeish])
+            .output()
+        {
+            Ok(out) if out.status.success() => out,
+            _ => return vec![],
+        };
+        String::from_utf8_lossy(&out.stdout)
+            .split('\0')
+            .filter_map(|entry| {
+                // <mode> SP <type> SP <sha> TAB <path>
+                let (info, path) = entry.split_once('\t')?;
+                let mut fields = info.split(' ').skip(1);
+                let is_tree = fields.next()? == "tree";
+                let sha = fields.next()?.to_owned();
+                Some(TreeEntry {
+                    path: path.to_owned(),
+                    sha,
+                    is_tree,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(feature = "native-git")]
+mod native {
+    use std::collections::{HashMap, HashSet};
+    use std::path::Path;
+    use std::sync::{Mutex, MutexGuard};
+
+    use git_wrapper::Repository;
+    use time::{OffsetDateTime, UtcOffset};
+
+    use super::{Author, TreeEntry};
+
+    /// Open the repository with the `GIT_DIR` git(1) is called with
+    pub(super) fn open(repo: &Repository) -> Option<git2::Repository> {
+        let cmd = repo.git();
+        let git_dir = cmd
+            .get_envs()
+            .find(|(key, _)| *key == "GIT_DIR")
+            .and_then(|(_, value)| value)?;
+        match git2::Repository::open(git_dir) {
+            Ok(native) => Some(native),
+            Err(e) => {
+                log::debug!("Falling back to git(1), libgit2 failed: {}", e);
+                None
+            }
+        }
+    }
+
+    pub(super) fn lock(native: &Mutex<git2::Repository>) -> MutexGuard<'_, git2::Repository> {
+        native
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    fn author(
+        native: &git2::Repository,
+        mailmap: Option<&git2::Mailmap>,
+        sha: &str,
+    ) -> Option<Author> {
+        let commit = native.find_commit(git2::Oid::from_str(sha).ok()?).ok()?;
+        let signature = match mailmap {
+            Some(map) => commit.author_with_mailmap(map).ok()?,
+            None => commit.author().to_owned(),
+        };
+        let when = signature.when();
+        let offset =
+            UtcOffset::from_whole_seconds(when.offset_minutes().saturating_mul(60)).ok()?;
+        let date = OffsetDateTime::from_unix_timestamp(when.seconds())
+            .ok()?
+            .to_offset(offset);
+        Some((
+            date,
+            String::from_utf8_lossy(signature.name_bytes()).to_string(),
+            String::from_utf8_lossy(signature.email_bytes()).to_string(),
+        ))
+    }
+
+    pub(super) fn authors<'a>(
+        native: &git2::Repository,
+        shas: impl IntoIterator<Item = &'a str>,
+    ) -> HashMap<String, Author> {
+        // Like %aN & %aE, names & emails are mapped with the .mailmap
+        let mailmap = native.mailmap().ok();
+        shas.into_iter()
+            .filter_map(|sha| Some((sha.to_owned(), author(native, mailmap.as_ref(), sha)?)))
+            .collect()
+    }
+
+    /// Return the id of the blob at `path` in a commit
+    fn blob_id(commit: &git2::Commit<'_>, path: &Path) -> Option<git2::Oid> {
+        commit
+            .tree()
+            .ok()?
+            .get_path(path)
+            .ok()
+            .map(|entry| entry.id())
+    }
+
+    /// Like git-log(1), a commit changed a file if it differs from the file in all parents.
+    /// `None` if `dir` is not in the working tree of the repository.
+    pub(super) fn last_changes(
+        native: &git2::Repository,
+        rev: Option<&str>,
+        dir: &Path,
+        files: &[&str],
+    ) -> Option<HashMap<String, Author>> {
+        let relative = dir.strip_prefix(native.workdir()?).ok()?;
+        let mut walk = native.revwalk().ok()?;
+        walk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)
+            .ok()?;
+        walk.push(
+            native
+                .revparse_single(rev.unwrap_or("HEAD"))
+                .ok()?
+                .peel_to_commit()
+                .ok()?
+                .id(),
+        )
+        .ok()?;
+        let mailmap = native.mailmap().ok();
+
+        let mut result = HashMap::new();
+        for oid in walk {
+            let commit = native.find_commit(oid.ok()?).ok()?;
+            let sha = commit.id().to_string();
+            let parents: Vec<git2::Commit<'_>> = commit.parents().collect();
+            // Skip commits which did not touch the directory at all
+            let subtree = blob_id(&commit, relative);
+            if subtree.is_none() || parents.iter().any(|p| blob_id(p, relative) == subtree) {
+                continue;
+            }
+            for file in files {
+                if result.contains_key(*file) {
+                    continue;
+                }
+                let path = relative.join(file);
+                let blob = match blob_id(&commit, &path) {
+                    Some(blob) => blob,
+                    None => continue,
+                };
+                if parents.iter().all(|p| blob_id(p, &path) != Some(blob)) {
+                    let author = author(native, mailmap.as_ref(), &sha)?;
+                    result.insert((*file).to_owned(), author);
+                }
+            }
+            if result.len() == files.len() {
+                break;
+            }
+        }
+        Some(result)
+    }
+
+    pub(super) fn blobs(
+        native: &git2::Repository,
+        shas: HashSet<&String>,
+    ) -> HashMap<String, String> {
+        shas.into_iter()
+            .filter_map(|sha| {
+                let blob = native.find_blob(git2::Oid::from_str(sha).ok()?).ok()?;
+                let text = String::from_utf8_lossy(blob.content())
+                    .trim_end()
+                    .to_owned();
+                Some((sha.clone(), text))
+            })
+            .collect()
+    }
+
+    pub(super) fn blob(native: &git2::Repository, name: &str) -> Option<String> {
+        let blob = native.revparse_single(name).ok()?.peel_to_blob().ok()?;
+        Some(String::from_utf8_lossy(blob.content()).to_string())
+    }
+
+    pub(super) fn ls_tree(native: &git2::Repository, treeish: &str) -> Vec<TreeEntry> {
+        let tree = match native
+            .revparse_single(treeish)
+            .and_then(|o| o.peel_to_tree())
+        {
+            Ok(tree) => tree,
+            Err(_) => return vec![],
+        };
+        let mut result = vec![];
+        let walked = tree.walk(git2::TreeWalkMode::PreOrder, |root, entry| {
+            let is_tree = match entry.kind() {
+                Some(git2::ObjectType::Tree) => true,
+                Some(git2::ObjectType::Blob) => false,
+                _ => return git2::TreeWalkResult::Ok,
+            };
+            result.push(TreeEntry {
+                path: format!("{}{}", root, String::from_utf8_lossy(entry.name_bytes())),
+                sha: entry.id().to_string(),
+                is_tree,
+            });
+            git2::TreeWalkResult::Ok
+        });
+        if let Err(e) = walked {
+            log::warn!("Failed to list {}: {}", treeish, e);
+        }
+        result
+    }
+}
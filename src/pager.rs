@@ -0,0 +1,117 @@
+use std::io::{IsTerminal, Stdout, Write};
+use std::process::{Child, Command, Stdio};
+
+use git_wrapper::Repository;
+
+/// Output stream writing either to stdout or through a pager
+///
+/// The pager is chosen like git(1) does: `$GIT_PAGER`, `core.pager`, `$PAGER` and finally
+/// `less`. No pager is used if stdout is not a terminal or the pager is empty or `cat`.
+/// Dropping the stream waits for the pager to exit.
+#[derive(Debug)]
+pub struct Output {
+    pager: Option<Child>,
+    stdout: Stdout,
+}
+
+impl Output {
+    /// Write directly to stdout
+    #[inline]
+    #[must_use]
+    pub fn stdout() -> Self {
+        Self {
+            pager: None,
+            stdout: std::io::stdout(),
+        }
+    }
+
+    /// Write through the configured pager if stdout is a terminal
+    #[inline]
+    #[must_use]
+    pub fn pager(repo: &Repository) -> Self {
+        if !std::io::stdout().is_terminal() {
+            return Self::stdout();
+        }
+        let pager = match pager_command(repo) {
+            None => return Self::stdout(),
+            Some(cmd) => cmd,
+        };
+        log::debug!("Using pager {}", pager);
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", &pager]).stdin(Stdio::piped());
+        if std::env::var_os("LESS").is_none() {
+            cmd.env("LESS", "FRX");
+        }
+        if std::env::var_os("LV").is_none() {
+            cmd.env("LV", "-c");
+        }
+        cmd.env("GIT_PAGER_IN_USE", "true");
+        match cmd.spawn() {
+            Ok(child) => Self {
+                pager: Some(child),
+                stdout: std::io::stdout(),
+            },
+            Err(e) => {
+                log::warn!("Failed to start pager {}: {}", pager, e);
+                Self::stdout()
+            }
+        }
+    }
+}
+
+/// Return the pager command or `None` if paging is disabled
+fn pager_command(repo: &Repository) -> Option<String> {
+    let configured = || {
+        let out = repo
+            .git()
+            .args(["config", "--get", "core.pager"])
+            .output()
+            .ok()?;
+        out.status
+            .success()
+            .then(|| String::from_utf8_lossy(&out.stdout).trim_end().to_owned())
+    };
+    let pager = std::env::var("GIT_PAGER")
+        .ok()
+        .or_else(configured)
+        .or_else(|| std::env::var("PAGER").ok())
+        .unwrap_or_else(|| "less".to_owned());
+    if pager.trim().is_empty() || pager.trim() == "cat" {
+        None
+    } else {
+        Some(pager)
+    }
+}
+
+impl Write for Output {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self.pager.as_mut().and_then(|child| child.stdin.as_mut()) {
+            Some(stdin) => stdin.write(buf),
+            None => self.stdout.write(buf),
+        }
+    }
+
+    #[inline]
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self.pager.as_mut().and_then(|child| child.stdin.as_mut()) {
+            Some(stdin) => stdin.flush(),
+            None => self.stdout.flush(),
+        }
+    }
+}
+
+impl Drop for Output {
+    #[inline]
+    fn drop(&mut self) {
+        if let Some(mut child) = self.pager.take() {
+            // Closing stdin signals the end of the output to the pager
+            drop(child.stdin.take());
+            if let Err(e) = child.wait() {
+                log::warn!("Failed to wait for pager: {}", e);
+            }
+        } else if let Err(e) = self.stdout.flush() {
+            log::debug!("Failed to flush stdout: {}", e);
+        }
+    }
+}
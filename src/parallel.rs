@@ -0,0 +1,41 @@
+use std::num::NonZeroUsize;
+
+/// Inputs smaller than this are processed on the current thread
+const MIN_PARALLEL: usize = 64;
+
+/// Apply `f` to all items on a pool of scoped threads, the results keep the order of `items`
+///
+/// The number of threads is limited by [`std::thread::available_parallelism`]. Small inputs
+/// are processed sequentially, because spawning threads would cost more than it saves.
+#[inline]
+pub fn par_map<T, R, F>(items: Vec<T>, f: F) -> Vec<R>
+where
+    T: Send,
+    R: Send,
+    F: Fn(T) -> R + Sync,
+{
+    let threads = std::thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    if threads < 2 || items.len() < MIN_PARALLEL {
+        return items.into_iter().map(f).collect();
+    }
+    let chunk_size = items.len().div_ceil(threads);
+    let mut chunks: Vec<Vec<T>> = vec![];
+    let mut rest = items.into_iter().peekable();
+    while rest.peek().is_some() {
+        chunks.push(rest.by_ref().take(chunk_size).collect());
+    }
+    let f = &f;
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = chunks
+            .into_iter()
+            .map(|chunk| scope.spawn(move || chunk.into_iter().map(f).collect::<Vec<R>>()))
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| match handle.join() {
+                Ok(results) => results,
+                Err(panic) => std::panic::resume_unwind(panic),
+            })
+            .collect()
+    })
+}
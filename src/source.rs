@@ -1,27 +1,26 @@
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
+use clap::ValueEnum;
 use clap_git_options::GitOptions;
-use git_wrapper::x;
+use getset::Getters;
 use git_wrapper::Repository;
 use time::format_description::well_known::Rfc3339;
 use time::OffsetDateTime;
 
+use crate::caching::{CacheError, DiskCache};
+use crate::change_set::ChangeSet;
 use crate::errors::{
-    FindError, FinishError, InitError, RollbackError, TransactionError, WriteError,
-    WritePropertyError,
+    FindError, InitError, IssueLoadError, TransactionError, WriteError, WritePropertyError,
 };
 use crate::id::CommentId;
 use crate::id::Id;
 use crate::issues::Comment;
+use crate::objects::Objects;
+use crate::store::{GitObjectStore, IssueStore, WorkTreeStore};
+use crate::transaction::{MergeStrategy, Transaction};
 use crate::Issue;
 
-/// Transaction struct
-#[derive(Debug)]
-pub struct Transaction {
-    start_sha: String,
-    stash_before: bool,
-}
-
 /// Returned by functions when data change is requested
 #[derive(Debug, PartialEq, Eq)]
 pub enum WriteResult {
@@ -41,40 +40,62 @@ impl From<Vec<Self>> for WriteResult {
     }
 }
 
-#[derive(Debug)]
+/// A property of an issue stored in its own file
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Property {
+    /// Comment with the given id
     Comment(String),
+    /// Title & description
     Description,
+    /// Due date
     DueDate,
+    /// Custom field, e.g. `assignee`
+    Field(String),
+    /// Tags
     Tags,
+    /// Milestone
     Milestone,
 }
 
 impl Property {
+    /// Return the path of the property file relative to the issue directory
+    #[must_use]
+    #[inline]
+    pub(crate) fn file_name(&self) -> String {
+        match self {
+            Self::Comment(id) => format!("comments/{}", id),
+            Self::Description => "description".to_owned(),
+            Self::DueDate => "duedate".to_owned(),
+            Self::Field(name) => name.clone(),
+            Self::Tags => "tags".to_owned(),
+            Self::Milestone => "milestone".to_owned(),
+        }
+    }
+
     #[must_use]
     #[inline]
-    fn path_buf(&self, issue_dir: &Path) -> PathBuf {
+    pub(crate) fn path_buf(&self, issue_dir: &Path) -> PathBuf {
         match self {
             Self::Comment(id) => issue_dir.join("comments").join(id),
-            Self::Description => issue_dir.join("description"),
-            Self::DueDate => issue_dir.join("duedate"),
-            Self::Tags => issue_dir.join("tags"),
-            Self::Milestone => issue_dir.join("milestone"),
+            _ => issue_dir.join(self.file_name()),
         }
     }
 }
-enum ChangeAction {
+
+#[derive(Debug)]
+pub(crate) enum ChangeAction {
     New,
     Edit,
 }
 
 #[derive(Debug)]
-enum Action {
+pub(crate) enum Action {
     Add,
     Remove,
 }
 
-enum CommitProperty {
+#[derive(Debug)]
+pub(crate) enum CommitProperty {
     Description {
         action: ChangeAction,
         id: String,
@@ -89,16 +110,17 @@ enum CommitProperty {
         milestone: String,
     },
 }
-impl CommitProperty {
-    #[must_use]
-    pub fn filename(&self) -> String {
-        match self {
-            Self::Description { .. } => "description",
-            Self::Tag { .. } => "tags",
-            Self::Milestone { .. } => "milestone",
-        }
-        .to_owned()
-    }
+
+/// Creation date & author of an issue
+#[derive(Clone, Debug, Getters, PartialEq, Eq)]
+#[getset(get = "pub")]
+pub struct CreationInfo {
+    /// Author date of the commit creating the issue
+    date: OffsetDateTime,
+    /// Author name
+    author_name: String,
+    /// Author email
+    author_email: String,
 }
 
 /// Use this to manipulate your issues
@@ -109,7 +131,19 @@ pub struct DataSource {
     pub repo: Repository,
     /// Path to `.issues` directory
     pub issues_dir: PathBuf,
-    transaction: Option<Transaction>,
+    revision: Option<Revision>,
+    store: Box<dyn IssueStore>,
+    objects: Objects,
+    strategy: Option<MergeStrategy>,
+}
+
+/// A commit the issues are read from instead of the working tree
+#[derive(Debug)]
+struct Revision {
+    /// Commit sha
+    sha: String,
+    /// Path of the `.issues` directory relative to the repository root, with a trailing `/`
+    prefix: String,
 }
 
 /// Vector of Strings containing tags
@@ -119,18 +153,230 @@ impl<'src> DataSource {
     /// Create new `DataSource` instance
     #[must_use]
     #[inline]
-    pub const fn new(issues_dir: PathBuf, repo: Repository) -> Self {
+    pub fn new(issues_dir: PathBuf, repo: Repository) -> Self {
+        let store = WorkTreeStore::new(repo.clone(), issues_dir.clone());
+        let objects = Objects::new(&repo);
         Self {
             repo,
             issues_dir,
-            transaction: None,
+            revision: None,
+            store: Box::new(store),
+            objects,
+            strategy: None,
+        }
+    }
+
+    /// Use the given storage for the issue files instead of the working tree
+    #[must_use]
+    #[inline]
+    pub fn with_store(mut self, store: Box<dyn IssueStore>) -> Self {
+        self.store = store;
+        self
+    }
+
+    /// Commit transactions with the given strategy instead of the configured one
+    #[must_use]
+    #[inline]
+    pub fn with_strategy(mut self, strategy: MergeStrategy) -> Self {
+        self.strategy = Some(strategy);
+        self
+    }
+
+    /// Return how transactions are committed
+    ///
+    /// The strategy set with [`Self::with_strategy`] wins over `issues.strategy` in
+    /// `.issues/config`. Without either, the changes are merged as not fast forward branch or,
+    /// with the `strict-compatibility` feature, kept as plain commits.
+    #[must_use]
+    #[inline]
+    pub fn merge_strategy(&self) -> MergeStrategy {
+        if let Some(strategy) = self.strategy {
+            return strategy;
+        }
+        match self.config("issues.strategy") {
+            Some(value) => MergeStrategy::from_str(&value, false).unwrap_or_else(|e| {
+                log::warn!("Invalid issues.strategy “{}”: {}", value, e);
+                MergeStrategy::default()
+            }),
+            None => MergeStrategy::default(),
         }
     }
 
+    /// Commit the issue changes directly to `reference`, e.g. `refs/heads/issues`, without
+    /// touching the working tree, the index or the stash
+    ///
+    /// Commits are built with a temporary index file, git-commit-tree(1) & git-update-ref(1).
+    /// A missing branch is created from `HEAD`. The issues are read from `reference` too.
+    ///
+    /// `HEAD` and branches checked out in any working tree are rejected, the index & the working
+    /// tree would not match the branch afterwards.
+    ///
+    /// # Errors
+    ///
+    /// Will throw an error if `reference` is checked out or does not exist and can not be created
+    #[inline]
+    pub fn with_ref(mut self, reference: &str) -> Result<Self, InitError> {
+        let prefix = self.prefix()?;
+        let full_name = self.full_name(reference);
+        let reference = full_name.as_deref().unwrap_or(reference);
+        if reference == "HEAD" || self.checked_out().iter().any(|b| b == reference) {
+            return Err(InitError::CheckedOut(reference.to_owned()));
+        }
+        if self.resolve(reference).is_none() {
+            let missing = || InitError::RevisionNotFound(reference.to_owned());
+            if !reference.starts_with("refs/") {
+                return Err(missing());
+            }
+            let head = self.resolve("HEAD").ok_or_else(missing)?;
+            log::info!("Creating {} from HEAD", reference);
+            let message = "git-issue: Create issue branch";
+            let out = self
+                .repo
+                .git()
+                .args(["update-ref", "-m", message, reference, &head, ""])
+                .output()
+                .map_err(|_err| missing())?;
+            if !out.status.success() {
+                return Err(missing());
+            }
+        }
+        log::debug!("Committing issues to {} at {:?}", reference, prefix);
+        self.store = Box::new(GitObjectStore::new(self.repo.clone(), reference, &prefix));
+        Ok(self)
+    }
+
+    /// Return the reference transactions commit to, `None` if they use the working tree
+    pub(crate) fn commit_ref(&self) -> Option<&str> {
+        self.store.commit_ref()
+    }
+
+    /// Return the full name of an existing reference, e.g. `refs/heads/main` for `main`
+    fn full_name(&self, reference: &str) -> Option<String> {
+        self.repo
+            .git()
+            .args([
+                "rev-parse",
+                "--verify",
+                "--quiet",
+                "--symbolic-full-name",
+                reference,
+            ])
+            .output()
+            .ok()
+            .filter(|out| out.status.success())
+            .map(|out| String::from_utf8_lossy(&out.stdout).trim().to_owned())
+            .filter(|name| !name.is_empty())
+    }
+
+    /// Return the branches checked out in the working trees of the repository
+    fn checked_out(&self) -> Vec<String> {
+        let out = match self
+            .repo
+            .git()
+            .args(["worktree", "list", "--porcelain"])
+            .output()
+        {
+            Ok(out) if out.status.success() => out,
+            _ => return vec![],
+        };
+        String::from_utf8_lossy(&out.stdout)
+            .lines()
+            .filter_map(|line| line.strip_prefix("branch "))
+            .map(ToOwned::to_owned)
+            .collect()
+    }
+
+    /// Return the commit `rev` points to
+    fn resolve(&self, rev: &str) -> Option<String> {
+        let commit = format!("{}^{{commit}}", rev);
+        self.repo
+            .git()
+            .args(["rev-parse", "--verify", "--quiet", &commit])
+            .output()
+            .ok()
+            .filter(|out| out.status.success())
+            .map(|out| String::from_utf8_lossy(&out.stdout).trim().to_owned())
+    }
+
+    /// Return the path of the `.issues` directory relative to the repository root
+    fn prefix(&self) -> Result<String, InitError> {
+        self.repo
+            .git()
+            .current_dir(&self.issues_dir)
+            .args(["rev-parse", "--show-prefix"])
+            .output()
+            .ok()
+            .filter(|out| out.status.success())
+            .map(|out| String::from_utf8_lossy(&out.stdout).trim().to_owned())
+            .ok_or(InitError::GitRepoNotFound)
+    }
+
+    /// Open the issues committed to `reference` in a repository without using its working tree,
+    /// e.g. a bare repository or a server side mirror
+    ///
+    /// `prefix` is the path of the issues directory in the tree: empty for a dedicated issues
+    /// repository as created by `git issue init`, `.issues/` for one embedded in a project.
+    ///
+    /// # Errors
+    ///
+    /// Will throw an error if `git_dir` is not a git repository
+    #[inline]
+    pub fn try_from_git_dir(
+        git_dir: &Path,
+        reference: &str,
+        prefix: &str,
+    ) -> Result<Self, InitError> {
+        let dir = Some(git_dir.to_str().ok_or(InitError::GitRepoNotFound)?);
+        // A bare repository has no working tree, plumbing commands do not need one
+        let repo =
+            Repository::from_args(None, dir, dir).map_err(|_err| InitError::GitRepoNotFound)?;
+        let store = GitObjectStore::new(repo.clone(), reference, prefix);
+        Ok(Self::new(git_dir.join(prefix), repo).with_store(Box::new(store)))
+    }
+
+    /// Read the issues from the given revision instead of the working tree
+    ///
+    /// The returned `DataSource` is read-only.
+    ///
+    /// # Errors
+    ///
+    /// Will throw an error if the revision can not be resolved to a commit
+    #[inline]
+    pub fn at(mut self, rev: &str) -> Result<Self, InitError> {
+        let sha = self
+            .resolve(rev)
+            .ok_or_else(|| InitError::RevisionNotFound(rev.to_owned()))?;
+        let prefix = self.prefix()?;
+        log::debug!("Reading issues from {} at {:?}", sha, prefix);
+        self.store = Box::new(GitObjectStore::new(self.repo.clone(), &sha, &prefix));
+        self.revision = Some(Revision { sha, prefix });
+        Ok(self)
+    }
+
+    /// Return the sha of the commit the issues are read from, `None` for the working tree
+    #[inline]
+    #[must_use]
+    pub fn revision(&self) -> Option<&str> {
+        self.revision.as_ref().map(|r| r.sha.as_str())
+    }
+
+    /// Return the revision the issues are read from, `None` for the working tree
+    ///
+    /// This is the [`Self::revision`] or the reference set with [`Self::with_ref`]. Commands
+    /// reading the history, like git-log(1), use it instead of `HEAD`.
+    pub(crate) fn read_rev(&self) -> Option<&str> {
+        self.revision().or_else(|| self.commit_ref())
+    }
+
+    /// Return the reader for commits, trees & blobs
+    pub(crate) const fn objects(&self) -> &Objects {
+        &self.objects
+    }
+
     /// Return an iterator over all issues
     #[inline]
     pub fn all(&'src self) -> impl Iterator<Item = std::io::Result<Issue<'src>>> {
-        self.all_ids().into_iter().map(|v| match v {
+        self.all_ids().map(|v| match v {
             Ok(id) => {
                 let i: Issue<'src> = Issue::new(self, id);
                 Ok(i)
@@ -139,29 +385,34 @@ impl<'src> DataSource {
         })
     }
 
+    /// Return all issues and the reasons why other issues were skipped
+    ///
+    /// Unlike [`Self::all`], directories which can not be read or whose name is not a valid
+    /// issue id are reported as [`IssueLoadError`]s instead of being returned.
+    #[inline]
+    #[must_use]
+    pub fn all_with_diagnostics(&'src self) -> (Vec<Issue<'src>>, Vec<IssueLoadError>) {
+        let dir = self.issues_dir.join("issues");
+        let mut issues = vec![];
+        let mut errors = vec![];
+        for result in self.all_ids() {
+            match result {
+                Ok(id) if id.is_valid() => issues.push(Issue::new(self, id)),
+                Ok(id) => {
+                    let msg = format!("Invalid issue id {}", id.id());
+                    let cause = std::io::Error::new(std::io::ErrorKind::InvalidData, msg);
+                    errors.push(IssueLoadError::new(invalid_path(&dir, &id), cause.into()));
+                }
+                Err(e) => errors.push(IssueLoadError::new(dir.clone(), e.into())),
+            }
+        }
+        (issues, errors)
+    }
+
     /// Return an iterator over all issue ids
     #[inline]
     fn all_ids(&self) -> impl Iterator<Item = std::io::Result<Id>> {
-        let path = self.issues_dir.join("issues");
-
-        let prefix_dirs = path.read_dir().expect("Directory").filter(dir_filter);
-        prefix_dirs.flat_map(Self::list_issue_dirs)
-    }
-
-    fn list_issue_dirs(
-        dir_entry_result: std::io::Result<std::fs::DirEntry>,
-    ) -> Box<dyn Iterator<Item = std::io::Result<Id>>> {
-        match dir_entry_result {
-            Ok(dir_entry) => match dir_entry.path().read_dir() {
-                Ok(ls) => Box::new(ls.filter(dir_filter).map(
-                    |result_dir_entry| -> std::io::Result<Id> {
-                        result_dir_entry.map(|p| p.path()).map(|p| Id::from(&p))
-                    },
-                )),
-                Err(e) => Box::new(vec![Err(e)].into_iter()),
-            },
-            Err(e) => Box::new(vec![Err(e)].into_iter()),
-        }
+        self.store.ids().into_iter()
     }
 
     /// # Errors
@@ -176,35 +427,125 @@ impl<'src> DataSource {
     ) -> Result<Id, WriteError> {
         let mark_text = "gi new mark";
         let message = format!("gi: Add issue\n\n{}", mark_text);
-        self.repo.commit_extended(&message, true, true)?;
-        let git_head = self.repo.head();
-        let id: Id = Id { id: git_head };
+        let id: Id = Id {
+            id: self.store.commit(&message, true)?,
+        };
         log::debug!("{} {:?}", mark_text, id);
 
-        self.new_description(&id, description)?;
-        log::debug!("gi new description {:?}", id);
-        for t in tags {
-            self.add_tag(&id, &t)?;
-            log::debug!("gi tag add {}", t);
+        #[cfg(feature = "strict-compatibility")]
+        {
+            self.new_description(&id, description)?;
+            log::debug!("gi new description {:?}", id);
+            for t in tags {
+                self.add_tag(&id, &t)?;
+                log::debug!("gi tag add {}", t);
+            }
+            if let Some(m) = milestone {
+                self.add_milestone(&id, &m)?;
+                log::debug!("gi milestone add {}", m);
+            }
         }
-        if let Some(m) = milestone {
-            self.add_milestone(&id, &m)?;
-            log::debug!("gi milestone add {}", m);
+        #[cfg(not(feature = "strict-compatibility"))]
+        {
+            let mut changes = self.change_set().new_description(&id, description);
+            for t in tags {
+                changes = changes.add_tag(&id, &t);
+            }
+            if let Some(m) = milestone {
+                changes = changes.add_milestone(&id, &m);
+            }
+            changes.commit()?;
+            log::debug!("gi new description {:?}", id);
         }
         Ok(id)
     }
 
+    /// Start a transaction, the returned guard rolls it back unless it is committed
+    ///
+    /// # Errors
+    ///
+    /// Will fail when stashing the changes in the repository fails
+    #[inline]
+    pub fn transaction(&self) -> Result<Transaction<'_>, TransactionError> {
+        Transaction::new(self)
+    }
+
+    /// Return an empty [`ChangeSet`] to commit several changes at once
+    #[inline]
+    pub fn change_set(&self) -> ChangeSet<'_> {
+        ChangeSet::new(self)
+    }
+
     /// Return the creation date
+    ///
+    /// # Errors
+    ///
+    /// Will throw an error if the id is not a commit or its date is invalid
     #[inline]
-    #[must_use]
-    pub fn creation_date(&self, id: &Id) -> OffsetDateTime {
-        let mut cmd = self.repo.git();
-        cmd.args(&["show", "--no-patch", "--format=%aI", id.id()]);
-        let out = cmd.output().expect("Failed to execute git-stash(1)");
+    pub fn creation_date(&self, id: &Id) -> Result<OffsetDateTime, CacheError> {
+        self.creation_info(id).map(|info| *info.date())
+    }
 
-        let output = String::from_utf8_lossy(&out.stdout);
-        let date_text = output.trim();
-        OffsetDateTime::parse(date_text, &Rfc3339).expect("Valid RFC-3339 date")
+    /// Look up the creation date & author of an issue
+    pub(crate) fn creation_info(&self, id: &Id) -> Result<CreationInfo, CacheError> {
+        let mut infos = self.creation_infos([id])?;
+        infos.remove(id).ok_or_else(|| {
+            let msg = format!("No commit {}", id.id());
+            CacheError::Io(std::io::Error::new(std::io::ErrorKind::NotFound, msg))
+        })
+    }
+
+    /// Return the git tree sha of every issue directory without uncommitted changes
+    pub(crate) fn trees(&self) -> HashMap<Id, String> {
+        self.store.trees()
+    }
+
+    /// Open the on-disk metadata cache `git-issue/cache` in the git directory
+    pub(crate) fn disk_cache(&self) -> Option<DiskCache> {
+        let out = self
+            .repo
+            .git()
+            .args(["rev-parse", "--git-path", "git-issue/cache"])
+            .output()
+            .ok()?;
+        if !out.status.success() {
+            return None;
+        }
+        let path = self
+            .repo
+            .work_tree()
+            .unwrap_or_default()
+            .join(String::from_utf8_lossy(&out.stdout).trim());
+        Some(DiskCache::load(path))
+    }
+
+    /// Return the creation date & author of many issues
+    ///
+    /// Without the `native-git` feature a single git-log(1) call is used. Ids which are not
+    /// commits in the repository are missing from the result.
+    ///
+    /// # Errors
+    ///
+    /// Will throw an error if executing git(1) fails or it returns an invalid date
+    #[inline]
+    pub fn creation_infos<'a>(
+        &self,
+        ids: impl IntoIterator<Item = &'a Id>,
+    ) -> Result<HashMap<Id, CreationInfo>, CacheError> {
+        let authors = self
+            .objects
+            .authors(ids.into_iter().map(|id| id.id().as_str()))?;
+        Ok(authors
+            .into_iter()
+            .map(|(sha, (date, author_name, author_email))| {
+                let info = CreationInfo {
+                    date,
+                    author_name,
+                    author_email,
+                };
+                (Id::new(sha), info)
+            })
+            .collect())
     }
 
     /// # Errors
@@ -212,6 +553,8 @@ impl<'src> DataSource {
     /// Will throw an error when:
     /// - Fails to find a non-bare git repository
     /// - Fails to resolve HEAD ref
+    /// - Fails to create the reference set as `issues.ref` in `.issues/config`, see
+    ///   [`Self::with_ref`]
     #[inline]
     pub fn try_new(options: &GitOptions) -> Result<Self, InitError> {
         let path = std::env::current_dir().expect("Failed to get CWD");
@@ -224,51 +567,62 @@ impl<'src> DataSource {
             Ok(repo) => Ok(repo),
             Err(_) => Err(InitError::GitRepoNotFound),
         }?;
-        Ok(Self {
-            repo,
-            issues_dir,
-            transaction: None,
-        })
+        Self::new(issues_dir, repo).with_configured_ref()
+    }
+
+    /// Commit to the reference set as `issues.ref` in `.issues/config`, see [`Self::with_ref`]
+    pub(crate) fn with_configured_ref(self) -> Result<Self, InitError> {
+        match self.config("issues.ref") {
+            Some(reference) => self.with_ref(&reference),
+            None => Ok(self),
+        }
+    }
+
+    /// Like [`Self::try_new`], reading the issues from a revision if one is given
+    ///
+    /// # Errors
+    ///
+    /// Will throw an error when [`Self::try_new`] fails or the revision can not be resolved
+    #[inline]
+    pub fn try_new_at(options: &GitOptions, rev: Option<&str>) -> Result<Self, InitError> {
+        let result = Self::try_new(options)?;
+        match rev {
+            Some(r) => result.at(r),
+            None => Ok(result),
+        }
     }
 
     /// Return all comment ids
     #[inline]
     #[must_use]
-    fn comment_ids(&self, id: &Id) -> Vec<std::io::Result<CommentId>> {
-        let comments_path = id.path(self.issues_dir.as_path()).join("comments");
-        if let Ok(dir) = comments_path.read_dir() {
-            dir.filter(file_filter)
-                .map(|d| Ok(CommentId::from(d?.path())))
-                .collect()
-        } else {
-            vec![]
-        }
+    pub(crate) fn comment_ids(&self, id: &Id) -> Vec<std::io::Result<CommentId>> {
+        self.store.comment_ids(id)
     }
 
     /// Return comments for issue
     #[inline]
     #[must_use]
     pub fn comments(&self, id: &Id) -> Vec<std::io::Result<Comment>> {
-        let mut result: Vec<_> = self
-            .comment_ids(id)
+        let ids = self.comment_ids(id);
+        let dir = id.path(&self.issues_dir).join("comments");
+        let names: Vec<&str> = ids
+            .iter()
+            .filter_map(|r| r.as_ref().ok().map(|cid| cid.id().as_str()))
+            .collect();
+        let invalid = |msg: String| std::io::Error::new(std::io::ErrorKind::InvalidData, msg);
+        let mut authors = match self.objects.last_changes(self.read_rev(), &dir, &names) {
+            Ok(authors) => authors,
+            Err(e) => return vec![Err(invalid(e.to_string()))],
+        };
+        let mut result: Vec<_> = ids
             .into_iter()
-            .map(|r| match r {
-                Ok(cid) => {
-                    let out = self
-                        .repo
-                        .git()
-                        .args(&["log", "-1", "--reverse", "--format=%aI\t%aN\t%h", "--"])
-                        .arg(id.path(&self.issues_dir).join("comments").join(cid.id()))
-                        .output()?;
-                    let output = String::from_utf8_lossy(&out.stdout);
-                    let split: Vec<_> = output.trim().splitn(3, '\t').collect();
-                    let body = self.read(id, &Property::Comment(cid.id().clone()))?;
-                    let cdate =
-                        OffsetDateTime::parse(split[0], &Rfc3339).expect("Valid RFC-3339 date");
-                    let author = split[1];
-                    Ok(Comment::new(cid, author.to_owned(), cdate, body))
-                }
-                Err(e) => Err(e),
+            .map(|r| {
+                let cid = r?;
+                let body = self.read(id, &Property::Comment(cid.id().clone()))?;
+                let (cdate, author, _) = authors
+                    .remove(cid.id())
+                    .ok_or_else(|| invalid(format!("Comment {} is not committed", cid.id())))?;
+                Ok(Comment::new(cid, author, cdate, body))
             })
             .collect();
 
@@ -299,64 +653,11 @@ impl<'src> DataSource {
     /// Returns an error if no issue matching id found or more than one issue are found.
     #[inline]
     pub fn find_issue(&self, needle: &str) -> Result<Id, FindError> {
-        match needle.len() {
-            1 => {
-                let path = self.issues_dir.join("issues");
-                let dirs: Vec<PathBuf> = list_dirs(&path);
-                if dirs.len() == 1 {
-                    let prefix = &dirs[0].file_name().expect("File name").to_str().expect("");
-                    self.find_issue(prefix)
-                } else {
-                    let ids: Vec<Id> = dirs
-                        .into_iter()
-                        .flat_map(|p: PathBuf| {
-                            list_dirs(&p).iter().map(Id::from).collect::<Vec<Id>>()
-                        })
-                        .collect();
-                    Err(FindError::MultipleFound(needle.to_owned(), ids))
-                }
-            }
-            2 => {
-                let path = self.issues_dir.join("issues").join(&needle);
-                if path.exists() {
-                    let dirs: Vec<PathBuf> = list_dirs(&path);
-                    match dirs.len() {
-                        0 => Err(FindError::NotFound(needle.to_owned())),
-                        1 => Ok(Id::from(&dirs[0])),
-                        _ => {
-                            let ids = dirs.iter().map(Id::from).collect();
-                            Err(FindError::MultipleFound(needle.to_owned(), ids))
-                        }
-                    }
-                } else {
-                    Err(FindError::NotFound(needle.to_owned()))
-                }
-            }
-            _ => {
-                {
-                    let path = self
-                        .issues_dir
-                        .join("issues")
-                        .join(&needle[..2])
-                        .join(&needle[2..]);
-                    if path.exists() {
-                        return Ok(Id {
-                            id: needle.to_owned(),
-                        });
-                    }
-                }
-                let path = self.issues_dir.join("issues").join(&needle[..2]);
-                let ids: Vec<Id> = list_dirs(&path)
-                    .iter()
-                    .map(Id::from)
-                    .filter(|id| id.id().starts_with(needle))
-                    .collect();
-                match ids.len() {
-                    0 => Err(FindError::NotFound(needle.to_owned())),
-                    1 => Ok(ids[0].clone()),
-                    _ => Err(FindError::MultipleFound(needle.to_owned(), ids)),
-                }
-            }
+        let ids = self.store.find_ids(needle);
+        match ids.len() {
+            0 => Err(FindError::NotFound(needle.to_owned())),
+            1 => Ok(ids[0].clone()),
+            _ => Err(FindError::MultipleFound(needle.to_owned(), ids)),
         }
     }
 
@@ -367,9 +668,17 @@ impl<'src> DataSource {
     /// Will throw error on failure to do IO
     #[inline]
     pub fn close_issue(&self, id: &Id) -> Result<WriteResult, WriteError> {
-        let remove_result = self.remove_tag(id, "open")?;
-        let add_result = self.add_tag(id, "closed")?;
-        Ok(WriteResult::from(vec![remove_result, add_result]))
+        #[cfg(feature = "strict-compatibility")]
+        {
+            let remove_result = self.remove_tag(id, "open")?;
+            let add_result = self.add_tag(id, "closed")?;
+            Ok(WriteResult::from(vec![remove_result, add_result]))
+        }
+        #[cfg(not(feature = "strict-compatibility"))]
+        self.change_set()
+            .remove_tag(id, "open")
+            .add_tag(id, "closed")
+            .commit()
     }
 
     fn find_issues_dir(p: &Path) -> Option<PathBuf> {
@@ -387,73 +696,28 @@ impl<'src> DataSource {
         }
     }
 
-    /// Start transaction
-    ///
-    /// # Errors
-    ///
-    /// Will fail when `HEAD` can not be resolved
-    #[inline]
-    pub fn start_transaction(&mut self) -> Result<(), TransactionError> {
-        let start_sha = self.repo.head();
-
-        let stash_before = !self.repo.is_clean();
-        let transaction = Transaction {
-            start_sha,
-            stash_before,
-        };
-        if stash_before {
-            log::debug!("Stashing repository changes");
-            self.repo.stash_almost_all("git-issue: Start Transaction")?;
-        }
-        self.transaction = Some(transaction);
-        Ok(())
-    }
-
-    /// Rolls back any commits made during the transaction and restores stashed changes if any.
-    ///
-    /// # Errors
-    ///
-    /// Throws an error when `git reset --hard` or poping stashed changes fails.
-    #[inline]
-    pub fn rollback_transaction(mut self) -> Result<(), TransactionError> {
-        let transaction = self.transaction.ok_or(TransactionError::NotStarted)?;
-        x::reset_hard(&self.repo, &transaction.start_sha).map_err(|e| {
-            if transaction.stash_before {
-                RollbackError::ResetUnstash(e.message())
-            } else {
-                RollbackError::Reset(e.message())
-            }
-        })?;
-        if transaction.stash_before {
-            log::debug!("Unstashing repository changes");
-            self.repo
-                .stash_pop()
-                .map_err(|e| RollbackError::Unstash(format!("{}", e)))?;
-        }
-        self.transaction = None;
-        Ok(())
-    }
-
     /// # Errors
     ///
     /// Will throw error on failure to read from file
     #[inline]
     pub(crate) fn read(&self, id: &Id, prop: &Property) -> std::io::Result<String> {
-        let path = prop.path_buf(&id.path(&self.issues_dir));
-        Ok(std::fs::read_to_string(path)?.trim_end().to_owned())
+        self.store.read(id, prop)
     }
 
     /// Returns duedate of an issue
     ///
     /// # Errors
     ///
-    /// Will throw error on failure to do IO
+    /// Will throw error on failure to do IO or if the due date is not a valid RFC 3339 date
     #[inline]
     pub fn duedate(&self, id: &Id) -> std::io::Result<Option<OffsetDateTime>> {
         match self.read(id, &Property::DueDate) {
-            Ok(date_text) => Ok(Some(
-                OffsetDateTime::parse(&date_text, &Rfc3339).expect("Valid RFC-3339 date"),
-            )),
+            Ok(date_text) => OffsetDateTime::parse(&date_text, &Rfc3339)
+                .map(Some)
+                .map_err(|e| {
+                    let msg = format!("Invalid due date of {}: {}", id.id(), e);
+                    std::io::Error::new(std::io::ErrorKind::InvalidData, msg)
+                }),
             Err(e) => match e.kind() {
                 std::io::ErrorKind::NotFound => Ok(None),
                 _ => Err(e),
@@ -576,6 +840,81 @@ impl<'src> DataSource {
         self.read(id, &Property::Milestone).ok()
     }
 
+    /// Returns the due date of a milestone if set.
+    ///
+    /// Due dates are read from `milestone.<name>.duedate` in `.issues/config` and may use any
+    /// date format git(1) understands.
+    #[must_use]
+    #[inline]
+    pub fn milestone_duedate(&self, milestone: &str) -> Option<OffsetDateTime> {
+        let key = format!("milestone.{}.duedate", milestone);
+        let value = self.config(&key)?;
+        crate::approxidate(&self.repo, &value)
+    }
+
+    /// Return the value of `key` in `.issues/config`
+    fn config(&self, key: &str) -> Option<String> {
+        let out = self
+            .repo
+            .git()
+            .arg("config")
+            .args(self.config_file())
+            .args(["--get", key])
+            .output()
+            .ok()?;
+        out.status
+            .success()
+            .then(|| String::from_utf8_lossy(&out.stdout).trim().to_owned())
+    }
+
+    /// Return the git-config(1) options selecting `.issues/config`
+    fn config_file(&self) -> Vec<String> {
+        match &self.revision {
+            Some(revision) => vec![
+                "--blob".to_owned(),
+                format!("{}:{}config", revision.sha, revision.prefix),
+            ],
+            None => vec![
+                "-f".to_owned(),
+                self.issues_dir.join("config").to_string_lossy().to_string(),
+            ],
+        }
+    }
+
+    /// Returns all milestones with a due date, sorted by name
+    #[must_use]
+    #[inline]
+    pub fn milestone_duedates(&self) -> Vec<(String, OffsetDateTime)> {
+        let out = match self
+            .repo
+            .git()
+            .arg("config")
+            .args(self.config_file())
+            .args(["--get-regexp", r"^milestone\..*\.duedate$"])
+            .output()
+        {
+            Ok(out) if out.status.success() => out,
+            _ => return vec![],
+        };
+        let mut result: Vec<(String, OffsetDateTime)> = String::from_utf8_lossy(&out.stdout)
+            .lines()
+            .filter_map(|line| {
+                let (key, value) = line.split_once(' ')?;
+                let name = key.strip_prefix("milestone.")?.strip_suffix(".duedate")?;
+                Some((name.to_owned(), crate::approxidate(&self.repo, value)?))
+            })
+            .collect();
+        result.sort_by(|a, b| a.0.cmp(&b.0));
+        result
+    }
+
+    /// Returns the value of a custom field like `assignee` or `weight` if set.
+    #[must_use]
+    #[inline]
+    pub fn field(&self, id: &Id, name: &str) -> Option<String> {
+        self.read(id, &Property::Field(name.to_owned())).ok()
+    }
+
     /// # Errors
     ///
     /// Will throw error on failure to read from description file
@@ -599,30 +938,22 @@ impl<'src> DataSource {
             .unwrap_or_default()
     }
 
-    fn write_to_file(&self, id: &Id, property: &CommitProperty) -> Result<(), WritePropertyError> {
-        let dir_path = id.path(&self.issues_dir);
-        if !dir_path.exists() {
-            std::fs::create_dir_all(&dir_path)?;
+    pub(crate) fn write_to_file(
+        &self,
+        id: &Id,
+        property: &CommitProperty,
+    ) -> Result<(), WritePropertyError> {
+        if let Some(revision) = &self.revision {
+            return Err(WritePropertyError::ReadOnly(revision.sha.clone()));
         }
-        let path = &dir_path.join(property.filename());
-
-        // Execute write
-        log::debug!("Writing {:?}", path);
-        match property {
-            CommitProperty::Description { description, .. } => {
-                std::fs::write(path, format!("{}\n", description.trim_end()))?;
-            }
+        let (prop, value) = match property {
+            CommitProperty::Description { description, .. } => (
+                Property::Description,
+                Some(format!("{}\n", description.trim_end())),
+            ),
             CommitProperty::Tag { tag, action, .. } => {
-                let value = std::fs::read_to_string(path);
-                let mut tags = if value.is_ok() && path.exists() {
-                    value
-                        .as_ref()
-                        .expect("value is set at this point")
-                        .lines()
-                        .collect::<Vec<&str>>()
-                } else {
-                    vec![]
-                };
+                let value = self.read(id, &Property::Tags).unwrap_or_default();
+                let mut tags = value.lines().collect::<Vec<&str>>();
                 match action {
                     Action::Add => {
                         tags.push(tag);
@@ -633,183 +964,149 @@ impl<'src> DataSource {
                 }
                 tags.sort_unstable();
                 tags.dedup();
-                std::fs::write(path, format!("{}\n", tags.join("\n")))?;
+                (Property::Tags, Some(format!("{}\n", tags.join("\n"))))
             }
             CommitProperty::Milestone {
                 milestone, action, ..
             } => match action {
-                Action::Add => {
-                    std::fs::write(path, format!("{}\n", milestone))?;
-                }
-                Action::Remove => {
-                    std::fs::remove_file(path)?;
-                }
+                Action::Add => (Property::Milestone, Some(format!("{}\n", milestone))),
+                Action::Remove => (Property::Milestone, None),
             },
         };
-
-        log::debug!("Staging {:?}", &path);
-        self.repo.stage(path).map_err(Into::into)
+        self.store.write(id, &prop, value.as_deref())
     }
 
     /// # Errors
     ///
     /// Will throw error on failure to do IO or commiting
-    fn write(&self, target_id: &Id, property: &CommitProperty) -> Result<(), WriteError> {
+    pub(crate) fn write(
+        &self,
+        target_id: &Id,
+        property: &CommitProperty,
+    ) -> Result<(), WriteError> {
         self.write_to_file(target_id, property)?;
-
-        let message = match property {
-            CommitProperty::Description {
-                action: ChangeAction::New,
-                id,
-                ..
-            } => format!("gi: Add issue description\n\ngi new description {}", id),
-            CommitProperty::Description {
-                action: ChangeAction::Edit,
-                id,
-                ..
-            } => format!("gi: Edit issue description\n\ngit edit description {}", id),
-            CommitProperty::Tag {
-                action: Action::Add,
-                tag,
-                ..
-            } => {
-                #[cfg(feature = "strict-compatibility")]
-                {
-                    format!("gi: Add tag\n\ngi tag add {}", tag)
-                }
-                #[cfg(not(feature = "strict-compatibility"))]
-                {
-                    format!(
-                        "gi({}): Add tag {}\n\ngi tag add {}",
-                        &target_id.short_id(),
-                        tag,
-                        tag
-                    )
-                }
-            }
-            CommitProperty::Tag {
-                action: Action::Remove,
-                tag,
-                ..
-            } => {
-                #[cfg(feature = "strict-compatibility")]
-                {
-                    format!("gi: Remove tag\n\ngi tag remove {}", tag)
-                }
-                #[cfg(not(feature = "strict-compatibility"))]
-                {
-                    format!(
-                        "gi({}): Remove tag {}\n\ngi tag remove {}",
-                        &target_id.short_id(),
-                        tag,
-                        tag
-                    )
-                }
-            }
-            CommitProperty::Milestone {
-                action: Action::Add,
-                milestone,
-                ..
-            } => {
-                #[cfg(feature = "strict-compatibility")]
-                {
-                    format!("gi: Add milestone\n\ngi milestone add {}", milestone)
-                }
-                #[cfg(not(feature = "strict-compatibility"))]
-                {
-                    format!(
-                        "gi({}): Add milestone {}\n\ngi milestone add {}",
-                        &target_id.short_id(),
-                        milestone,
-                        milestone
-                    )
-                }
-            }
-            CommitProperty::Milestone {
-                action: Action::Remove,
-                milestone,
-                ..
-            } => {
-                #[cfg(feature = "strict-compatibility")]
-                {
-                    format!("gi: Remove milestone\n\ngi milestone remove {}", milestone)
-                }
-                #[cfg(not(feature = "strict-compatibility"))]
-                {
-                    format!(
-                        "gi({}): Remove milestone {}\n\ngi milestone remove {}",
-                        &target_id.short_id(),
-                        milestone,
-                        milestone
-                    )
-                }
-            }
-        };
-
-        self.repo
-            .commit_extended(&message, false, true)
-            .map_err(Into::into)
+        let message = format!("{}\n\n{}", subject(target_id, property), marker(property));
+        self.store.commit(&message, false).map(drop)
     }
 
+    /// Commit the changes written with [`Self::write_to_file`]
+    ///
     /// # Errors
     ///
     /// Will throw error on failure to commit
-    #[inline]
-    pub fn finish_transaction_without_merge(&mut self) -> Result<(), TransactionError> {
-        let transaction = &self.transaction.as_ref().expect("A started transaction");
-        if transaction.stash_before {
-            log::debug!("Unstashing repository changes");
-            self.repo
-                .stash_pop()
-                .map_err(|e| FinishError::Unstash(format!("{}", e)))?;
+    #[cfg(not(feature = "strict-compatibility"))]
+    pub(crate) fn commit_written(&self, message: &str) -> Result<(), WriteError> {
+        self.store.commit(message, false).map(drop)
+    }
+}
+
+/// Return the directory of an issue with an invalid id in the `issues` directory `dir`
+///
+/// The parent directory of an invalid issue is not necessarily named after the first two bytes
+/// of its id, so the split of the id naming an existing directory is used.
+fn invalid_path(dir: &Path, id: &Id) -> PathBuf {
+    let text = id.id();
+    let mut fallback = None;
+    for (n, (i, _)) in text.char_indices().skip(1).enumerate() {
+        let path = dir.join(&text[..i]).join(&text[i..]);
+        if path.is_dir() {
+            return path;
+        }
+        if n == 1 {
+            fallback = Some(path);
         }
-        self.transaction = None;
-        Ok(())
     }
+    fallback.unwrap_or_else(|| dir.join(text))
+}
 
-    /// # Errors
-    ///
-    /// Will throw error on failure to commit
-    #[allow(unused_variables)]
-    #[inline]
-    pub fn finish_transaction(&mut self, message: &str) -> Result<(), TransactionError> {
-        let transaction = &self.transaction.as_ref().expect("A started transaction");
+/// Return the subject of the commit message for a single property change
+pub(crate) fn subject(target_id: &Id, property: &CommitProperty) -> String {
+    #[cfg(feature = "strict-compatibility")]
+    let _unused = target_id;
+    match property {
+        CommitProperty::Description {
+            action: ChangeAction::New,
+            ..
+        } => "gi: Add issue description".to_owned(),
+        CommitProperty::Description {
+            action: ChangeAction::Edit,
+            ..
+        } => "gi: Edit issue description".to_owned(),
+        #[cfg(feature = "strict-compatibility")]
+        CommitProperty::Tag { action, .. } => match action {
+            Action::Add => "gi: Add tag".to_owned(),
+            Action::Remove => "gi: Remove tag".to_owned(),
+        },
+        #[cfg(feature = "strict-compatibility")]
+        CommitProperty::Milestone { action, .. } => match action {
+            Action::Add => "gi: Add milestone".to_owned(),
+            Action::Remove => "gi: Remove milestone".to_owned(),
+        },
         #[cfg(not(feature = "strict-compatibility"))]
-        {
-            log::info!("Merging issue changes as not fast forward branch");
-            let sha = self.repo.head();
-            x::reset_hard(&self.repo, &transaction.start_sha).map_err(|e| {
-                if transaction.stash_before {
-                    TransactionError::FinishError(FinishError::ResetUnstash(e.message()))
-                } else {
-                    TransactionError::FinishError(FinishError::Reset(e.message()))
-                }
-            })?;
+        _ => format!("gi({}): {}", target_id.short_id(), change(property)),
+    }
+}
 
-            let mut cmd = self.repo.git();
-            let out = cmd
-                .args(&["merge", "--no-ff", "-m", message, &sha])
-                .output()
-                .expect("Failed to execute git-stash(1)");
+/// Return a short description of a property change, e.g. `Add tag bug`
+#[cfg(not(feature = "strict-compatibility"))]
+pub(crate) fn change(property: &CommitProperty) -> String {
+    match property {
+        CommitProperty::Description {
+            action: ChangeAction::New,
+            ..
+        } => "Add description".to_owned(),
+        CommitProperty::Description {
+            action: ChangeAction::Edit,
+            ..
+        } => "Edit description".to_owned(),
+        CommitProperty::Tag {
+            action: Action::Add,
+            tag,
+        } => format!("Add tag {}", tag),
+        CommitProperty::Tag {
+            action: Action::Remove,
+            tag,
+        } => format!("Remove tag {}", tag),
+        CommitProperty::Milestone {
+            action: Action::Add,
+            milestone,
+        } => format!("Add milestone {}", milestone),
+        CommitProperty::Milestone {
+            action: Action::Remove,
+            milestone,
+        } => format!("Remove milestone {}", milestone),
+    }
+}
 
-            if !out.status.success() {
-                let output = String::from_utf8_lossy(&out.stderr).to_string();
-                if transaction.stash_before {
-                    return Err(TransactionError::FinishError(FinishError::MergeUnstash(
-                        output,
-                    )));
-                }
-                return Err(TransactionError::FinishError(FinishError::Merge(output)));
-            }
-        }
-        if transaction.stash_before {
-            log::debug!("Unstashing repository changes");
-            self.repo
-                .stash_pop()
-                .map_err(|e| FinishError::Unstash(format!("{}", e)))?;
-        }
-        self.transaction = None;
-        Ok(())
+/// Return the marker line of the commit message body for a single property change
+pub(crate) fn marker(property: &CommitProperty) -> String {
+    match property {
+        CommitProperty::Description {
+            action: ChangeAction::New,
+            id,
+            ..
+        } => format!("gi new description {}", id),
+        CommitProperty::Description {
+            action: ChangeAction::Edit,
+            id,
+            ..
+        } => format!("git edit description {}", id),
+        CommitProperty::Tag {
+            action: Action::Add,
+            tag,
+        } => format!("gi tag add {}", tag),
+        CommitProperty::Tag {
+            action: Action::Remove,
+            tag,
+        } => format!("gi tag remove {}", tag),
+        CommitProperty::Milestone {
+            action: Action::Add,
+            milestone,
+        } => format!("gi milestone add {}", milestone),
+        CommitProperty::Milestone {
+            action: Action::Remove,
+            milestone,
+        } => format!("gi milestone remove {}", milestone),
     }
 }
 
@@ -827,30 +1124,3 @@ impl TryFrom<&Path> for DataSource {
         Ok(Self::new(issues_dir, repo))
     }
 }
-
-fn dir_filter(read_dir_result: &std::io::Result<std::fs::DirEntry>) -> bool {
-    read_dir_result
-        .as_ref()
-        .map(|dir_entry| dir_entry.metadata().map(|d| d.is_dir()).unwrap_or(false))
-        .unwrap_or(false)
-}
-
-fn file_filter(read_dir_result: &std::io::Result<std::fs::DirEntry>) -> bool {
-    read_dir_result
-        .as_ref()
-        .map(|dir_entry| dir_entry.metadata().map(|d| d.is_file()).unwrap_or(false))
-        .unwrap_or(false)
-}
-
-fn list_dirs(path: &Path) -> Vec<PathBuf> {
-    if !path.exists() {
-        return vec![];
-    }
-    let paths: Vec<PathBuf> = path
-        .read_dir()
-        .expect("Directory")
-        .filter(dir_filter)
-        .map(|d| d.expect("IO Successful").path())
-        .collect();
-    paths
-}
@@ -0,0 +1,701 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+
+use git_wrapper::{CommitError, Repository};
+
+use crate::errors::{WriteError, WritePropertyError};
+use crate::id::{CommentId, Id};
+use crate::objects::Objects;
+use crate::source::Property;
+
+/// Storage of the issue files
+///
+/// Paths are relative to the `.issues/issues` directory, e.g. `ab/cdef…/tags`.
+pub trait IssueStore: std::fmt::Debug + Send + Sync {
+    /// Return the ids of all issues
+    fn ids(&self) -> Vec<std::io::Result<Id>>;
+
+    /// Return the ids of all issues starting with `prefix`
+    ///
+    /// The default implementation filters [`IssueStore::ids`], unreadable issues are skipped.
+    #[inline]
+    fn find_ids(&self, prefix: &str) -> Vec<Id> {
+        filter_ids(self.ids(), prefix)
+    }
+
+    /// Return the ids of all comments of an issue
+    fn comment_ids(&self, id: &Id) -> Vec<std::io::Result<CommentId>>;
+
+    /// Read a property, the trailing white space is removed
+    ///
+    /// # Errors
+    ///
+    /// Will throw [`std::io::ErrorKind::NotFound`] if the property is not set
+    fn read(&self, id: &Id, property: &Property) -> std::io::Result<String>;
+
+    /// Write a property or remove it if `value` is `None`
+    ///
+    /// The change is recorded by the next [`IssueStore::commit`].
+    ///
+    /// # Errors
+    ///
+    /// Will throw an error if the change can not be written or staged
+    fn write(
+        &self,
+        id: &Id,
+        property: &Property,
+        value: Option<&str>,
+    ) -> Result<(), WritePropertyError>;
+
+    /// Commit all written changes and return the sha of the new commit
+    ///
+    /// # Errors
+    ///
+    /// Will throw an error if there is nothing to commit and `allow_empty` is `false` or
+    /// committing fails
+    fn commit(&self, message: &str, allow_empty: bool) -> Result<String, WriteError>;
+
+    /// Return the git tree sha of every issue directory without uncommitted changes
+    ///
+    /// Only issues listed here are served from the on-disk cache. The default implementation
+    /// returns an empty map, which disables the cache.
+    #[inline]
+    fn trees(&self) -> HashMap<Id, String> {
+        HashMap::new()
+    }
+
+    /// Return the reference which is committed to without using the working tree
+    ///
+    /// Transactions on such a store update the reference instead of stashing the changes in the
+    /// working tree and resetting `HEAD`. The default implementation returns `None`.
+    #[inline]
+    fn commit_ref(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// Return the path of a property relative to the `.issues/issues` directory
+fn property_path(id: &Id, property: &Property) -> String {
+    format!(
+        "{}/{}/{}",
+        &id.id()[..2],
+        &id.id()[2..],
+        property.file_name()
+    )
+}
+
+/// Return the readable ids starting with `prefix`
+fn filter_ids(ids: Vec<std::io::Result<Id>>, prefix: &str) -> Vec<Id> {
+    ids.into_iter()
+        .filter_map(Result::ok)
+        .filter(|id| id.id().starts_with(prefix))
+        .collect()
+}
+
+/// Return the issue ids of paths relative to the `.issues/issues` directory
+fn ids_from_paths<'a>(paths: impl Iterator<Item = &'a String>) -> Vec<std::io::Result<Id>> {
+    let mut ids: Vec<String> = paths
+        .filter_map(|path| {
+            let mut components = path.splitn(3, '/');
+            match (components.next(), components.next(), components.next()) {
+                (Some(prefix), Some(rest), Some(_)) => Some(format!("{}{}", prefix, rest)),
+                _ => None,
+            }
+        })
+        .collect();
+    ids.sort_unstable();
+    ids.dedup();
+    ids.into_iter().map(|id| Ok(Id::new(id))).collect()
+}
+
+/// Return the comment ids of an issue from paths relative to the `.issues/issues` directory
+fn comment_ids_from_paths<'a>(
+    id: &Id,
+    paths: impl Iterator<Item = &'a String>,
+) -> Vec<std::io::Result<CommentId>> {
+    let dir = format!("{}/{}/comments/", &id.id()[..2], &id.id()[2..]);
+    paths
+        .filter_map(|path| path.strip_prefix(&dir))
+        .filter(|name| !name.contains('/'))
+        .map(|name| Ok(CommentId::from(name.to_owned())))
+        .collect()
+}
+
+/// Return the trees of the issue directories in the `.issues/issues` tree `treeish`
+fn issue_trees(objects: &Objects, treeish: &str) -> HashMap<Id, String> {
+    objects
+        .ls_tree(treeish)
+        .into_iter()
+        .filter(|entry| entry.is_tree)
+        .filter_map(|entry| {
+            let (prefix, rest) = entry.path.split_once('/')?;
+            if rest.contains('/') {
+                return None;
+            }
+            Some((Id::new(format!("{}{}", prefix, rest)), entry.sha))
+        })
+        .collect()
+}
+
+/// Return the issue id of a path relative to the `.issues/issues` directory
+fn id_of_path(path: &str) -> Option<Id> {
+    let mut components = path.splitn(3, '/');
+    match (components.next(), components.next()) {
+        (Some(prefix), Some(rest)) if !rest.is_empty() => {
+            Some(Id::new(format!("{}{}", prefix, rest)))
+        }
+        _ => None,
+    }
+}
+
+fn not_found(what: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::NotFound, format!("{} not found", what))
+}
+
+fn git_failure(out: &std::process::Output) -> WriteError {
+    let msg = String::from_utf8_lossy(&out.stderr).to_string();
+    WriteError::CommitError(CommitError::Failure(msg, out.status.code().unwrap_or(1)))
+}
+
+/// Issue files in the working tree, changes are committed with git-commit(1)
+#[derive(Debug)]
+pub struct WorkTreeStore {
+    repo: Repository,
+    issues_dir: PathBuf,
+}
+
+impl WorkTreeStore {
+    /// Create a store for the given `.issues` directory
+    #[inline]
+    #[must_use]
+    pub const fn new(repo: Repository, issues_dir: PathBuf) -> Self {
+        Self { repo, issues_dir }
+    }
+}
+
+fn dir_filter(read_dir_result: &std::io::Result<std::fs::DirEntry>) -> bool {
+    read_dir_result
+        .as_ref()
+        .map(|dir_entry| dir_entry.metadata().map(|d| d.is_dir()).unwrap_or(false))
+        .unwrap_or(false)
+}
+
+fn file_filter(read_dir_result: &std::io::Result<std::fs::DirEntry>) -> bool {
+    read_dir_result
+        .as_ref()
+        .map(|dir_entry| dir_entry.metadata().map(|d| d.is_file()).unwrap_or(false))
+        .unwrap_or(false)
+}
+
+fn list_issue_dirs(
+    dir_entry_result: std::io::Result<std::fs::DirEntry>,
+) -> Vec<std::io::Result<Id>> {
+    match dir_entry_result.and_then(|dir_entry| dir_entry.path().read_dir()) {
+        Ok(ls) => ls
+            .filter(dir_filter)
+            .map(|result_dir_entry| result_dir_entry.map(|p| Id::from(&p.path())))
+            .collect(),
+        Err(e) => vec![Err(e)],
+    }
+}
+
+impl IssueStore for WorkTreeStore {
+    #[inline]
+    fn ids(&self) -> Vec<std::io::Result<Id>> {
+        let path = self.issues_dir.join("issues");
+        if !path.exists() {
+            return vec![];
+        }
+        match path.read_dir() {
+            Ok(prefix_dirs) => prefix_dirs
+                .filter(dir_filter)
+                .flat_map(list_issue_dirs)
+                .collect(),
+            Err(e) => vec![Err(e)],
+        }
+    }
+
+    #[inline]
+    fn find_ids(&self, prefix: &str) -> Vec<Id> {
+        // Only the directory named after the first two characters is read
+        let (dir, rest) = match (prefix.get(..2), prefix.get(2..)) {
+            (Some(dir), Some(rest)) if !rest.is_empty() => (dir, rest),
+            _ => return filter_ids(self.ids(), prefix),
+        };
+        let dir = self.issues_dir.join("issues").join(dir);
+        let id = Id::new(prefix.to_owned());
+        if id.is_valid() && dir.join(rest).is_dir() {
+            return vec![id];
+        }
+        let entries = match dir.read_dir() {
+            Ok(entries) => entries,
+            Err(_) => return vec![],
+        };
+        let ids = entries
+            .filter(dir_filter)
+            .map(|entry| entry.map(|e| Id::from(&e.path())))
+            .collect();
+        filter_ids(ids, prefix)
+    }
+
+    #[inline]
+    fn comment_ids(&self, id: &Id) -> Vec<std::io::Result<CommentId>> {
+        let comments_path = id.path(self.issues_dir.as_path()).join("comments");
+        if let Ok(dir) = comments_path.read_dir() {
+            dir.filter(file_filter)
+                .map(|d| Ok(CommentId::from(d?.path())))
+                .collect()
+        } else {
+            vec![]
+        }
+    }
+
+    #[inline]
+    fn read(&self, id: &Id, property: &Property) -> std::io::Result<String> {
+        let path = property.path_buf(&id.path(&self.issues_dir));
+        Ok(std::fs::read_to_string(path)?.trim_end().to_owned())
+    }
+
+    #[inline]
+    fn write(
+        &self,
+        id: &Id,
+        property: &Property,
+        value: Option<&str>,
+    ) -> Result<(), WritePropertyError> {
+        let path = property.path_buf(&id.path(&self.issues_dir));
+        log::debug!("Writing {:?}", path);
+        match value {
+            Some(text) => {
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(&path, text)?;
+            }
+            None => std::fs::remove_file(&path)?,
+        }
+
+        log::debug!("Staging {:?}", &path);
+        self.repo.stage(&path).map_err(Into::into)
+    }
+
+    #[inline]
+    fn commit(&self, message: &str, allow_empty: bool) -> Result<String, WriteError> {
+        self.repo.commit_extended(message, allow_empty, true)?;
+        Ok(self.repo.head())
+    }
+
+    #[inline]
+    fn trees(&self) -> HashMap<Id, String> {
+        let work_tree = self.repo.work_tree().unwrap_or_default();
+        let relative = |path: &Path| -> Option<String> {
+            let top = work_tree.canonicalize().ok()?;
+            let dir = path.canonicalize().ok()?;
+            Some(dir.strip_prefix(top).ok()?.to_string_lossy().to_string())
+        };
+        let prefix = match relative(&self.issues_dir) {
+            Some(dir) if dir.is_empty() => "issues/".to_owned(),
+            Some(dir) => format!("{}/issues/", dir),
+            None => return HashMap::new(),
+        };
+        let objects = Objects::new(&self.repo);
+        let mut result = issue_trees(&objects, &format!("HEAD:{}", prefix));
+        if result.is_empty() {
+            return result;
+        }
+
+        // Issues with staged, unstaged or untracked changes are not served from the cache
+        let out = match self
+            .repo
+            .git()
+            .args(["status", "--porcelain", "-z", "--untracked-files=all", "--"])
+            .arg(self.issues_dir.join("issues"))
+            .output()
+        {
+            Ok(out) if out.status.success() => out,
+            _ => return HashMap::new(),
+        };
+        for entry in String::from_utf8_lossy(&out.stdout).split('\0') {
+            // Entries start with a two letter status, the source of a rename does not
+            let path = entry
+                .strip_prefix(&prefix)
+                .or_else(|| entry.get(3..).and_then(|p| p.strip_prefix(&prefix)));
+            if let Some(id) = path.and_then(id_of_path) {
+                result.remove(&id);
+            }
+        }
+        result
+    }
+}
+
+/// Issue files in git objects, works in bare repositories
+///
+/// Files are read from the tree of a reference. Changes are kept in memory and committed on top
+/// of the reference with git-commit-tree(1), neither the working tree nor the index are
+/// touched.
+#[derive(Debug)]
+pub struct GitObjectStore {
+    repo: Repository,
+    reference: String,
+    prefix: String,
+    pending: Mutex<BTreeMap<String, Option<String>>>,
+    objects: Objects,
+}
+
+impl GitObjectStore {
+    /// Create a store reading from and committing to `reference`, e.g. `HEAD` or
+    /// `refs/heads/issues`
+    ///
+    /// `prefix` is the path of the `.issues` directory in the tree, e.g. `.issues/`, with a
+    /// trailing slash.
+    #[inline]
+    #[must_use]
+    pub fn new(repo: Repository, reference: &str, prefix: &str) -> Self {
+        let objects = Objects::new(&repo);
+        Self {
+            repo,
+            reference: reference.to_owned(),
+            prefix: prefix.to_owned(),
+            pending: Mutex::new(BTreeMap::new()),
+            objects,
+        }
+    }
+
+    /// Return the reference the files are read from
+    #[inline]
+    #[must_use]
+    pub fn reference(&self) -> &str {
+        &self.reference
+    }
+
+    fn pending(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, Option<String>>> {
+        self.pending
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    fn git(&self) -> Command {
+        self.repo.git()
+    }
+
+    /// Return the commit the reference points to, `None` for an unborn branch
+    fn parent(&self) -> Option<String> {
+        let commit = format!("{}^{{commit}}", self.reference);
+        let out = self
+            .git()
+            .args(["rev-parse", "--verify", "--quiet", &commit])
+            .output()
+            .ok()?;
+        out.status
+            .success()
+            .then(|| String::from_utf8_lossy(&out.stdout).trim().to_owned())
+    }
+
+    /// Return all committed & pending paths of issue files
+    fn paths(&self) -> Vec<String> {
+        self.paths_in("")
+    }
+
+    /// Return the committed & pending paths of the issue files below `dir`, e.g. `ab/cdef…`
+    ///
+    /// The paths are relative to the `issues` directory like the ones of [`Self::paths`], but
+    /// only the tree of `dir` is listed.
+    fn paths_in(&self, dir: &str) -> Vec<String> {
+        let (tree, prefix) = if dir.is_empty() {
+            (
+                format!("{}:{}issues", self.reference, self.prefix),
+                String::new(),
+            )
+        } else {
+            let tree = format!("{}:{}issues/{}", self.reference, self.prefix, dir);
+            (tree, format!("{}/", dir))
+        };
+        let mut result: Vec<String> = self
+            .objects
+            .ls_tree(&tree)
+            .into_iter()
+            .filter(|entry| !entry.is_tree)
+            .map(|entry| format!("{}{}", prefix, entry.path))
+            .collect();
+        for (path, value) in self.pending().iter() {
+            if !path.starts_with(&prefix) {
+                continue;
+            }
+            match value {
+                Some(_) if !result.contains(path) => result.push(path.clone()),
+                Some(_) => {}
+                None => result.retain(|p| p != path),
+            }
+        }
+        result
+    }
+
+    /// Run a git command with the given index file and return its trimmed output
+    fn run(&self, index: &Path, args: &[&str], input: Option<&str>) -> Result<String, WriteError> {
+        let mut cmd = self.git();
+        cmd.env("GIT_INDEX_FILE", index)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let mut child = cmd.spawn().map_err(WritePropertyError::from)?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(input.unwrap_or_default().as_bytes())
+                .map_err(WritePropertyError::from)?;
+        }
+        let out = child.wait_with_output().map_err(WritePropertyError::from)?;
+        if !out.status.success() {
+            return Err(git_failure(&out));
+        }
+        Ok(String::from_utf8_lossy(&out.stdout).trim().to_owned())
+    }
+
+    /// Create the commit using a temporary index file
+    fn commit_with_index(
+        &self,
+        index: &Path,
+        message: &str,
+        allow_empty: bool,
+    ) -> Result<String, WriteError> {
+        let parent = self.parent();
+        match &parent {
+            Some(sha) => self.run(index, &["read-tree", sha], None)?,
+            None => self.run(index, &["read-tree", "--empty"], None)?,
+        };
+        let pending: Vec<(String, Option<String>)> = self
+            .pending()
+            .iter()
+            .map(|(path, value)| (path.clone(), value.clone()))
+            .collect();
+        for (path, value) in pending {
+            let full_path = format!("{}issues/{}", self.prefix, path);
+            match value {
+                Some(text) => {
+                    let blob = self.run(index, &["hash-object", "-w", "--stdin"], Some(&text))?;
+                    let info = format!("100644,{},{}", blob, full_path);
+                    self.run(
+                        index,
+                        &["update-index", "--add", "--cacheinfo", &info],
+                        None,
+                    )?;
+                }
+                None => {
+                    self.run(index, &["update-index", "--force-remove", &full_path], None)?;
+                }
+            }
+        }
+        let tree = self.run(index, &["write-tree"], None)?;
+        if let (Some(sha), false) = (&parent, allow_empty) {
+            let parent_tree =
+                self.run(index, &["rev-parse", &format!("{}^{{tree}}", sha)], None)?;
+            if parent_tree == tree {
+                return Err(WriteError::CommitError(CommitError::Failure(
+                    "nothing to commit".to_owned(),
+                    1,
+                )));
+            }
+        }
+        let mut args = vec!["commit-tree", tree.as_str(), "-m", message];
+        if let Some(sha) = &parent {
+            args.extend(["-p", sha.as_str()]);
+        }
+        let commit = self.run(index, &args, None)?;
+        let old = parent.unwrap_or_else(|| "0".repeat(40));
+        let reflog = format!("git-issue: {}", message.lines().next().unwrap_or_default());
+        self.run(
+            index,
+            &["update-ref", "-m", &reflog, &self.reference, &commit, &old],
+            None,
+        )?;
+        Ok(commit)
+    }
+}
+
+impl IssueStore for GitObjectStore {
+    #[inline]
+    fn ids(&self) -> Vec<std::io::Result<Id>> {
+        ids_from_paths(self.paths().iter())
+    }
+
+    #[inline]
+    fn find_ids(&self, prefix: &str) -> Vec<Id> {
+        match prefix.get(..2) {
+            Some(dir) if prefix.len() > 2 => {
+                filter_ids(ids_from_paths(self.paths_in(dir).iter()), prefix)
+            }
+            _ => filter_ids(self.ids(), prefix),
+        }
+    }
+
+    #[inline]
+    fn comment_ids(&self, id: &Id) -> Vec<std::io::Result<CommentId>> {
+        let dir = format!("{}/{}/comments", &id.id()[..2], &id.id()[2..]);
+        comment_ids_from_paths(id, self.paths_in(&dir).iter())
+    }
+
+    #[inline]
+    fn read(&self, id: &Id, property: &Property) -> std::io::Result<String> {
+        let path = property_path(id, property);
+        if let Some(value) = self.pending().get(&path) {
+            return value
+                .as_ref()
+                .map(|v| v.trim_end().to_owned())
+                .ok_or_else(|| not_found(&path));
+        }
+        let object = format!("{}:{}issues/{}", self.reference, self.prefix, path);
+        match self.objects.blob(&object)? {
+            Some(text) => Ok(text.trim_end().to_owned()),
+            None => Err(not_found(&object)),
+        }
+    }
+
+    #[inline]
+    fn write(
+        &self,
+        id: &Id,
+        property: &Property,
+        value: Option<&str>,
+    ) -> Result<(), WritePropertyError> {
+        let path = property_path(id, property);
+        log::debug!("Writing {} to {}", path, self.reference);
+        self.pending().insert(path, value.map(ToOwned::to_owned));
+        Ok(())
+    }
+
+    #[inline]
+    fn commit(&self, message: &str, allow_empty: bool) -> Result<String, WriteError> {
+        let out = self
+            .git()
+            .args(["rev-parse", "--absolute-git-dir"])
+            .output()
+            .map_err(WritePropertyError::from)?;
+        let git_dir = PathBuf::from(String::from_utf8_lossy(&out.stdout).trim());
+        // Every commit gets its own index, concurrent commits would mix their files otherwise.
+        // The directory is removed on drop.
+        let tmp_dir = tempfile::Builder::new()
+            .prefix("git-issue.index.")
+            .tempdir_in(git_dir)
+            .map_err(WritePropertyError::from)?;
+        let result = self.commit_with_index(&tmp_dir.path().join("index"), message, allow_empty);
+        if result.is_ok() {
+            self.pending().clear();
+        }
+        result
+    }
+
+    #[inline]
+    fn commit_ref(&self) -> Option<&str> {
+        Some(&self.reference)
+    }
+
+    #[inline]
+    fn trees(&self) -> HashMap<Id, String> {
+        let tree = format!("{}:{}issues", self.reference, self.prefix);
+        let mut result = issue_trees(&self.objects, &tree);
+        for path in self.pending().keys() {
+            if let Some(id) = id_of_path(path) {
+                result.remove(&id);
+            }
+        }
+        result
+    }
+}
+
+/// Issue files kept in memory, useful for tests
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    files: Mutex<BTreeMap<String, String>>,
+    commits: Mutex<Vec<String>>,
+    dirty: Mutex<bool>,
+}
+
+impl MemoryStore {
+    /// Create an empty store
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the messages of all commits, oldest first
+    #[inline]
+    #[must_use]
+    pub fn messages(&self) -> Vec<String> {
+        self.commits
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .clone()
+    }
+
+    fn files(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, String>> {
+        self.files
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+impl IssueStore for MemoryStore {
+    #[inline]
+    fn ids(&self) -> Vec<std::io::Result<Id>> {
+        ids_from_paths(self.files().keys())
+    }
+
+    #[inline]
+    fn comment_ids(&self, id: &Id) -> Vec<std::io::Result<CommentId>> {
+        comment_ids_from_paths(id, self.files().keys())
+    }
+
+    #[inline]
+    fn read(&self, id: &Id, property: &Property) -> std::io::Result<String> {
+        let path = property_path(id, property);
+        self.files()
+            .get(&path)
+            .map(|v| v.trim_end().to_owned())
+            .ok_or_else(|| not_found(&path))
+    }
+
+    #[inline]
+    fn write(
+        &self,
+        id: &Id,
+        property: &Property,
+        value: Option<&str>,
+    ) -> Result<(), WritePropertyError> {
+        let path = property_path(id, property);
+        match value {
+            Some(text) => self.files().insert(path, text.to_owned()),
+            None => self.files().remove(&path),
+        };
+        *self
+            .dirty
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner) = true;
+        Ok(())
+    }
+
+    #[inline]
+    fn commit(&self, message: &str, allow_empty: bool) -> Result<String, WriteError> {
+        let mut dirty = self
+            .dirty
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        if !*dirty && !allow_empty {
+            return Err(WriteError::CommitError(CommitError::Failure(
+                "nothing to commit".to_owned(),
+                1,
+            )));
+        }
+        *dirty = false;
+        let mut commits = self
+            .commits
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        commits.push(message.to_owned());
+        // The sha only needs to be unique within the store
+        Ok(format!("{:040x}", commits.len()))
+    }
+}
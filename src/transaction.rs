@@ -0,0 +1,350 @@
+//! Group the commits of an operation, see [`DataSource::transaction`]
+
+use git_wrapper::{x, Repository};
+
+use crate::errors::{FinishError, RollbackError, TransactionError};
+use crate::DataSource;
+
+/// How the commits of a transaction end up on the branch
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum MergeStrategy {
+    /// Merge the commits as not fast forward branch with the message of the operation
+    #[cfg_attr(not(feature = "strict-compatibility"), default)]
+    MergeNoFf,
+    /// Keep the commits as they are, like git-issue(1) does
+    #[cfg_attr(feature = "strict-compatibility", default)]
+    FastForward,
+    /// Replace the commits with a single one with the message of the operation
+    Squash,
+}
+
+/// Where a transaction started
+#[derive(Debug)]
+enum Start {
+    /// The changes in the working tree are stashed, `HEAD` is reset on roll back
+    WorkTree {
+        start_sha: String,
+        stash_before: bool,
+    },
+    /// Commits are made on `reference` directly, the working tree is not touched
+    Ref {
+        reference: String,
+        start_sha: Option<String>,
+    },
+}
+
+/// Run git(1) and return its trimmed output or its error message
+fn git(repo: &Repository, args: &[&str]) -> Result<String, String> {
+    let out = repo.git().args(args).output().map_err(|e| e.to_string())?;
+    if out.status.success() {
+        Ok(String::from_utf8_lossy(&out.stdout).trim().to_owned())
+    } else {
+        Err(String::from_utf8_lossy(&out.stderr).to_string())
+    }
+}
+
+/// Return the commit `reference` points to, `None` for an unborn branch
+fn resolve(repo: &Repository, reference: &str) -> Option<String> {
+    let commit = format!("{}^{{commit}}", reference);
+    git(repo, &["rev-parse", "--verify", "--quiet", &commit]).ok()
+}
+
+/// Return `true` if both commits have the same tree, i.e. the changes between them cancel out
+fn same_tree(repo: &Repository, a: &str, b: &str) -> bool {
+    let tree = |rev: &str| {
+        git(
+            repo,
+            &[
+                "rev-parse",
+                "--verify",
+                "--quiet",
+                &format!("{}^{{tree}}", rev),
+            ],
+        )
+    };
+    matches!((tree(a), tree(b)), (Ok(a), Ok(b)) if a == b)
+}
+
+/// Create a commit with the tree of `head` & the given parents, then point `reference` to it
+fn commit_tree(
+    repo: &Repository,
+    reference: &str,
+    head: &str,
+    parents: &[&str],
+    message: &str,
+) -> Result<(), String> {
+    let tree = format!("{}^{{tree}}", head);
+    let mut args = vec!["commit-tree", tree.as_str(), "-m", message];
+    for parent in parents {
+        args.extend(["-p", parent]);
+    }
+    let commit = git(repo, &args)?;
+    let reflog = format!("git-issue: {}", message.lines().next().unwrap_or_default());
+    git(
+        repo,
+        &["update-ref", "-m", &reflog, reference, &commit, head],
+    )
+    .map(drop)
+}
+
+impl Start {
+    /// Remember `HEAD` and stash the changes in the repository
+    fn new(repo: &Repository) -> Result<Self, TransactionError> {
+        let start_sha = repo.head();
+        let stash_before = !repo.is_clean();
+        if stash_before {
+            log::debug!("Stashing repository changes");
+            repo.stash_almost_all("git-issue: Start Transaction")?;
+        }
+        Ok(Self::WorkTree {
+            start_sha,
+            stash_before,
+        })
+    }
+
+    /// Remember the commit `reference` points to
+    fn on_ref(repo: &Repository, reference: &str) -> Self {
+        Self::Ref {
+            reference: reference.to_owned(),
+            start_sha: resolve(repo, reference),
+        }
+    }
+
+    /// Reset `HEAD` or the reference to the start and restore the stashed changes
+    fn rollback(&self, repo: &Repository) -> Result<(), TransactionError> {
+        match self {
+            Self::WorkTree {
+                start_sha,
+                stash_before,
+            } => {
+                x::reset_hard(repo, start_sha).map_err(|e| {
+                    if *stash_before {
+                        RollbackError::ResetUnstash(e.message())
+                    } else {
+                        RollbackError::Reset(e.message())
+                    }
+                })?;
+                if *stash_before {
+                    log::debug!("Unstashing repository changes");
+                    repo.stash_pop()
+                        .map_err(|e| RollbackError::Unstash(format!("{}", e)))?;
+                }
+            }
+            Self::Ref {
+                reference,
+                start_sha,
+            } => {
+                if resolve(repo, reference) == *start_sha {
+                    return Ok(());
+                }
+                let message = "git-issue: Roll back transaction";
+                let result = match start_sha {
+                    Some(sha) => git(repo, &["update-ref", "-m", message, reference, sha]),
+                    None => git(repo, &["update-ref", "-d", reference]),
+                };
+                result.map_err(|e| RollbackError::UpdateRef(e, reference.clone()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Merge the commits made since the start as not fast forward branch
+    fn merge(&self, repo: &Repository, message: &str) -> Result<(), TransactionError> {
+        log::info!("Merging issue changes as not fast forward branch");
+        let (start_sha, stash_before) = match self {
+            Self::WorkTree {
+                start_sha,
+                stash_before,
+            } => (start_sha, *stash_before),
+            Self::Ref {
+                reference,
+                start_sha: Some(start_sha),
+            } => {
+                // The start is an ancestor of the head, the merge has the tree of the head
+                return match resolve(repo, reference) {
+                    Some(head) if head != *start_sha => {
+                        commit_tree(repo, reference, &head, &[start_sha, &head], message)
+                            .map_err(|e| FinishError::Merge(e).into())
+                    }
+                    _ => Ok(()),
+                };
+            }
+            // Nothing to merge into
+            Self::Ref { .. } => return Ok(()),
+        };
+        let sha = repo.head();
+        x::reset_hard(repo, start_sha).map_err(|e| {
+            if stash_before {
+                FinishError::ResetUnstash(e.message())
+            } else {
+                FinishError::Reset(e.message())
+            }
+        })?;
+
+        let out = repo
+            .git()
+            .args(["merge", "--no-ff", "-m", message, &sha])
+            .output()
+            .map_err(|e| FinishError::Merge(format!("{}", e)))?;
+        if !out.status.success() {
+            let output = String::from_utf8_lossy(&out.stderr).to_string();
+            if stash_before {
+                return Err(FinishError::MergeUnstash(output).into());
+            }
+            return Err(FinishError::Merge(output).into());
+        }
+        Ok(())
+    }
+
+    /// Replace the commits made since the start with a single commit
+    fn squash(&self, repo: &Repository, message: &str) -> Result<(), TransactionError> {
+        let start_sha = match self {
+            Self::WorkTree { start_sha, .. } => start_sha,
+            Self::Ref {
+                reference,
+                start_sha,
+            } => {
+                let head = resolve(repo, reference);
+                return match (head, start_sha) {
+                    (Some(head), Some(start)) if head == *start => Ok(()),
+                    // Nothing to commit, drop the commits
+                    (Some(head), Some(start)) if same_tree(repo, &head, start) => {
+                        log::info!("Issue changes cancel out, dropping them");
+                        let reflog = "git-issue: Squash canceled out changes";
+                        git(repo, &["update-ref", "-m", reflog, reference, start, &head])
+                            .map(drop)
+                            .map_err(|e| FinishError::Squash(e).into())
+                    }
+                    (Some(head), _) => {
+                        log::info!("Squashing issue changes into one commit");
+                        let parents: Vec<&str> = start_sha.iter().map(String::as_str).collect();
+                        commit_tree(repo, reference, &head, &parents, message)
+                            .map_err(|e| FinishError::Squash(e).into())
+                    }
+                    (None, _) => Ok(()),
+                };
+            }
+        };
+        let head = repo.head();
+        if head == *start_sha {
+            return Ok(());
+        }
+        let reset = ["reset", "--quiet", "--soft", start_sha];
+        if same_tree(repo, &head, start_sha) {
+            // git-commit(1) refuses to commit nothing, drop the commits
+            log::info!("Issue changes cancel out, dropping them");
+            return git(repo, &reset)
+                .map(drop)
+                .map_err(|e| FinishError::Squash(e).into());
+        }
+        log::info!("Squashing issue changes into one commit");
+        let commit = ["commit", "--quiet", "--no-verify", "-m", message];
+        for args in [&reset[..], &commit[..]] {
+            git(repo, args).map_err(FinishError::Squash)?;
+        }
+        Ok(())
+    }
+
+    /// Restore the stashed changes
+    fn unstash(&self, repo: &Repository) -> Result<(), TransactionError> {
+        if let Self::WorkTree {
+            stash_before: true, ..
+        } = self
+        {
+            log::debug!("Unstashing repository changes");
+            repo.stash_pop()
+                .map_err(|e| FinishError::Unstash(format!("{}", e)))?;
+        }
+        Ok(())
+    }
+}
+
+/// Guard for the commits made by an operation
+///
+/// Created by [`DataSource::transaction`], which stashes the changes in the repository. The
+/// commits made while the guard is alive are merged with [`Transaction::commit`]. Dropping the
+/// guard without committing, e.g. on an early return or a panic, resets `HEAD` to where the
+/// transaction started and restores the stashed changes.
+///
+/// If the issues are committed to a reference with [`DataSource::with_ref`], only that reference
+/// is updated or reset, the working tree & the stash are left alone.
+#[derive(Debug)]
+#[must_use = "Dropping a transaction rolls it back"]
+pub struct Transaction<'src> {
+    src: &'src DataSource,
+    strategy: MergeStrategy,
+    start: Option<Start>,
+}
+
+impl<'src> Transaction<'src> {
+    pub(crate) fn new(src: &'src DataSource) -> Result<Self, TransactionError> {
+        // Read before stashing, an uncommitted .issues/config counts
+        let strategy = src.merge_strategy();
+        let start = match src.commit_ref() {
+            Some(reference) => Start::on_ref(&src.repo, reference),
+            None => Start::new(&src.repo)?,
+        };
+        Ok(Self {
+            src,
+            strategy,
+            start: Some(start),
+        })
+    }
+
+    fn take(&mut self) -> Result<Start, TransactionError> {
+        self.start.take().ok_or(TransactionError::NotStarted)
+    }
+
+    /// Finish the transaction, the commits are merged according to the
+    /// [`DataSource::merge_strategy`] at the start using the given message
+    ///
+    /// # Errors
+    ///
+    /// Will throw error on failure to merge or to restore the stashed changes. The transaction is
+    /// rolled back if merging fails.
+    #[inline]
+    pub fn commit(mut self, message: &str) -> Result<(), TransactionError> {
+        // The start stays in the guard until the merge succeeded, so a failure is rolled back
+        let start = self.start.as_ref().ok_or(TransactionError::NotStarted)?;
+        match self.strategy {
+            MergeStrategy::MergeNoFf => start.merge(&self.src.repo, message)?,
+            MergeStrategy::FastForward => {}
+            MergeStrategy::Squash => start.squash(&self.src.repo, message)?,
+        }
+        self.take()?.unstash(&self.src.repo)
+    }
+
+    /// Finish the transaction keeping the commits as they are
+    ///
+    /// # Errors
+    ///
+    /// Will throw error on failure to restore the stashed changes
+    #[inline]
+    pub fn commit_without_merge(mut self) -> Result<(), TransactionError> {
+        self.take()?.unstash(&self.src.repo)
+    }
+
+    /// Roll back the commits made during the transaction and restore the stashed changes
+    ///
+    /// Same as dropping the guard, but reports failures.
+    ///
+    /// # Errors
+    ///
+    /// Throws an error when `git reset --hard` or popping the stashed changes fails.
+    #[inline]
+    pub fn rollback(mut self) -> Result<(), TransactionError> {
+        self.take()?.rollback(&self.src.repo)
+    }
+}
+
+impl Drop for Transaction<'_> {
+    #[inline]
+    fn drop(&mut self) {
+        if let Some(start) = self.start.take() {
+            log::info!("Rolling back transaction");
+            if let Err(e) = start.rollback(&self.src.repo) {
+                log::error!("{}", e);
+            }
+        }
+    }
+}